@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use bluer::Uuid;
+
+use crate::camera::INSTAX_SERVICE_UUID;
+
+// Bluetooth SIG company identifier of FUJIFILM Corporation
+pub const FUJIFILM_COMPANY_ID: u16 = 0x04d8;
+
+// State a camera broadcasts in its advertisements, readable without connecting
+// The payload layout is not documented and the one below is a guess, not
+// checked against a capture yet. Fields are None when the payload is too short:
+//   byte 0  model id
+//   byte 1  flags: bit 0 awake (accepts connections), bit 1 charging
+//   byte 2  battery level, same units as CameraFunctionInfo::battery_level
+// The raw payloads are kept so the guess can be checked against real cameras.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Advertisement {
+    pub model_id: Option<u8>,
+    pub is_awake: Option<bool>,
+    pub is_charging: Option<bool>,
+    pub battery_level: Option<u8>,
+    pub manufacturer_data: Option<Vec<u8>>,
+    pub service_data: Option<Vec<u8>>,
+}
+
+impl Advertisement {
+    // Takes the advertisement maps as reported by bluer::Device
+    pub fn parse(manufacturer_data: &HashMap<u16, Vec<u8>>, service_data: &HashMap<Uuid, Vec<u8>>) -> Self {
+        let mut advertisement = Advertisement {
+            manufacturer_data: manufacturer_data.get(&FUJIFILM_COMPANY_ID).cloned(),
+            service_data: service_data.get(&INSTAX_SERVICE_UUID).cloned(),
+            ..Default::default()
+        };
+        // Service data is used by cameras without manufacturer data
+        let payload = advertisement.manufacturer_data.clone().or(advertisement.service_data.clone());
+        if let Some(payload) = payload {
+            // Partial payloads still give the fields they cover
+            advertisement.decode(&payload);
+        }
+        advertisement
+    }
+
+    fn decode(&mut self, payload: &[u8]) -> Option<()> {
+        self.model_id = Some(*payload.first()?);
+        let flags = *payload.get(1)?;
+        self.is_awake = Some(flags & 0x01 != 0);
+        self.is_charging = Some(flags & 0x02 != 0);
+        self.battery_level = Some(payload.get(2)? & 0x0f);
+        Some(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.manufacturer_data.is_none() && self.service_data.is_none()
+    }
+}
@@ -0,0 +1,128 @@
+// Minimal MJPEG-in-AVI writer used to assemble interval recordings
+// Each JPEG frame is stored unchanged as a '00dc' chunk of a single video stream.
+// Reference: https://learn.microsoft.com/en-us/windows/win32/directshow/avi-riff-file-reference
+
+const AVIF_HASINDEX: u32 = 0x10;
+const AVIIF_KEYFRAME: u32 = 0x10;
+
+// Width and height from the first SOFn marker of a JPEG image
+pub fn jpeg_dimensions(jpeg: &[u8]) -> Option<(u16, u16)> {
+    if jpeg.get(0..2)? != [0xFF, 0xD8] {
+        return None;
+    }
+    let mut i = 2;
+    while i + 4 <= jpeg.len() {
+        if jpeg[i] != 0xFF {
+            return None;
+        }
+        let marker = jpeg[i + 1];
+        if marker == 0xFF {
+            // Fill byte
+            i += 1;
+            continue;
+        }
+        let length = u16::from_be_bytes([jpeg[i + 2], jpeg[i + 3]]) as usize;
+        let is_sof = (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_sof {
+            let sof = jpeg.get(i + 5..i + 9)?;
+            let height = u16::from_be_bytes([sof[0], sof[1]]);
+            let width = u16::from_be_bytes([sof[2], sof[3]]);
+            return Some((width, height));
+        }
+        i += 2 + length;
+    }
+    None
+}
+
+fn chunk(fourcc: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 9);
+    out.extend(fourcc);
+    out.extend((data.len() as u32).to_le_bytes());
+    out.extend(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+    out
+}
+
+fn list(fourcc: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut body = fourcc.to_vec();
+    body.extend(data);
+    chunk(b"LIST", &body)
+}
+
+// Build an AVI file from JPEG frames played back at `fps`
+// Frame size is taken from the first frame
+pub fn write_mjpeg_avi(frames: &[Vec<u8>], fps: u32) -> Vec<u8> {
+    let (width, height) = frames.first()
+        .and_then(|frame| jpeg_dimensions(frame))
+        .unwrap_or((0, 0));
+    let (width, height) = (width as u32, height as u32);
+    let num_frames = frames.len() as u32;
+    let max_frame = frames.iter().map(Vec::len).max().unwrap_or(0) as u32;
+
+    let mut avih = Vec::new();
+    avih.extend((1_000_000 / fps.max(1)).to_le_bytes()); // dwMicroSecPerFrame
+    avih.extend(max_frame.saturating_mul(fps).to_le_bytes()); // dwMaxBytesPerSec
+    avih.extend(0u32.to_le_bytes()); // dwPaddingGranularity
+    avih.extend(AVIF_HASINDEX.to_le_bytes()); // dwFlags
+    avih.extend(num_frames.to_le_bytes()); // dwTotalFrames
+    avih.extend(0u32.to_le_bytes()); // dwInitialFrames
+    avih.extend(1u32.to_le_bytes()); // dwStreams
+    avih.extend(max_frame.to_le_bytes()); // dwSuggestedBufferSize
+    avih.extend(width.to_le_bytes());
+    avih.extend(height.to_le_bytes());
+    avih.extend([0; 16]); // dwReserved
+
+    let mut strh = Vec::new();
+    strh.extend(b"vids");
+    strh.extend(b"MJPG");
+    strh.extend(0u32.to_le_bytes()); // dwFlags
+    strh.extend(0u16.to_le_bytes()); // wPriority
+    strh.extend(0u16.to_le_bytes()); // wLanguage
+    strh.extend(0u32.to_le_bytes()); // dwInitialFrames
+    strh.extend(1u32.to_le_bytes()); // dwScale
+    strh.extend(fps.to_le_bytes()); // dwRate
+    strh.extend(0u32.to_le_bytes()); // dwStart
+    strh.extend(num_frames.to_le_bytes()); // dwLength
+    strh.extend(max_frame.to_le_bytes()); // dwSuggestedBufferSize
+    strh.extend(u32::MAX.to_le_bytes()); // dwQuality
+    strh.extend(0u32.to_le_bytes()); // dwSampleSize
+    strh.extend(0u16.to_le_bytes()); // rcFrame
+    strh.extend(0u16.to_le_bytes());
+    strh.extend((width as u16).to_le_bytes());
+    strh.extend((height as u16).to_le_bytes());
+
+    // BITMAPINFOHEADER
+    let mut strf = Vec::new();
+    strf.extend(40u32.to_le_bytes()); // biSize
+    strf.extend(width.to_le_bytes());
+    strf.extend(height.to_le_bytes());
+    strf.extend(1u16.to_le_bytes()); // biPlanes
+    strf.extend(24u16.to_le_bytes()); // biBitCount
+    strf.extend(b"MJPG"); // biCompression
+    strf.extend(width.saturating_mul(height).saturating_mul(3).to_le_bytes()); // biSizeImage
+    strf.extend([0; 16]); // biXPelsPerMeter, biYPelsPerMeter, biClrUsed, biClrImportant
+
+    let mut strl = chunk(b"strh", &strh);
+    strl.extend(chunk(b"strf", &strf));
+    let mut hdrl = chunk(b"avih", &avih);
+    hdrl.extend(list(b"strl", &strl));
+
+    // Index offsets are relative to the 'movi' fourcc
+    let mut movi = Vec::new();
+    let mut idx1 = Vec::new();
+    for frame in frames {
+        idx1.extend(b"00dc");
+        idx1.extend(AVIIF_KEYFRAME.to_le_bytes());
+        idx1.extend((movi.len() as u32 + 4).to_le_bytes());
+        idx1.extend((frame.len() as u32).to_le_bytes());
+        movi.extend(chunk(b"00dc", frame));
+    }
+
+    let mut riff = b"AVI ".to_vec();
+    riff.extend(list(b"hdrl", &hdrl));
+    riff.extend(list(b"movi", &movi));
+    riff.extend(chunk(b"idx1", &idx1));
+    chunk(b"RIFF", &riff)
+}
@@ -0,0 +1,94 @@
+use chrono::prelude::*;
+
+use crate::{CameraFunctionInfo, ChargerStateType};
+
+// One CAMERA_FUNCTION_INFO reading
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatterySample {
+    pub time: DateTime<Local>,
+    pub battery_level: u8,
+    pub is_charging: bool,
+    pub charger_state: ChargerStateType,
+}
+
+impl BatterySample {
+    pub fn new(info: &CameraFunctionInfo) -> Self {
+        BatterySample {
+            time: Local::now(),
+            battery_level: info.battery_level,
+            is_charging: info.is_charging,
+            charger_state: info.charger_state,
+        }
+    }
+
+    pub const CSV_HEADER: &'static str = "time,battery_level,is_charging,charger_state";
+
+    pub fn to_csv(&self) -> String {
+        format!("{},{},{},{:?}", self.time.to_rfc3339(), self.battery_level, self.is_charging, self.charger_state)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BatteryEvent {
+    // Battery level dropped below the threshold
+    Low(u8),
+    // Battery level rose back to the threshold or above
+    Recovered(u8),
+    ChargingStarted,
+    ChargingStopped,
+    ChargerStateChanged(ChargerStateType),
+}
+
+// Tracks battery readings and reports threshold crossings and charging changes
+// Thresholds use the same units as CameraFunctionInfo::battery_level
+pub struct BatteryMonitor {
+    thresholds: Vec<u8>,
+    last: Option<BatterySample>,
+}
+
+impl BatteryMonitor {
+    pub fn new(thresholds: Vec<u8>) -> Self {
+        BatteryMonitor { thresholds, last: None }
+    }
+
+    pub fn last(&self) -> Option<&BatterySample> {
+        self.last.as_ref()
+    }
+
+    // Record a sample and return the events it triggers
+    // The first sample only reports thresholds the level is already below
+    pub fn update(&mut self, sample: BatterySample) -> Vec<BatteryEvent> {
+        let mut events = Vec::new();
+        match &self.last {
+            None => {
+                for &threshold in &self.thresholds {
+                    if sample.battery_level < threshold {
+                        events.push(BatteryEvent::Low(threshold));
+                    }
+                }
+            }
+            Some(last) => {
+                for &threshold in &self.thresholds {
+                    if last.battery_level >= threshold && sample.battery_level < threshold {
+                        events.push(BatteryEvent::Low(threshold));
+                    }
+                    if last.battery_level < threshold && sample.battery_level >= threshold {
+                        events.push(BatteryEvent::Recovered(threshold));
+                    }
+                }
+                match (last.is_charging, sample.is_charging) {
+                    (false, true) => events.push(BatteryEvent::ChargingStarted),
+                    (true, false) => events.push(BatteryEvent::ChargingStopped),
+                    _ => {}
+                }
+                if last.charger_state != sample.charger_state {
+                    events.push(BatteryEvent::ChargerStateChanged(sample.charger_state));
+                }
+            }
+        }
+        self.last = Some(sample);
+        events
+    }
+}
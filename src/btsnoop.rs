@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use bluer::{Uuid, UuidExt};
+
+use crate::camera::{INSTAX_NOTIFY_UUID, INSTAX_WRITE_UUID};
+use crate::packet;
+use crate::recording::{Frame, FrameDirection, Recording};
+
+// Imports Instax frames from btsnoop HCI logs, such as Android's
+// btsnoop_hci.log captured while the official app talks to a camera.
+//
+// File layout (RFC 1761), big endian:
+//   header: "btsnoop\0" + version(4) + datalink(4)
+//   record: original length(4) + included length(4) + flags(4) + drops(4) + timestamp(8) + data
+// HCI, L2CAP and ATT fields inside the records are little endian.
+
+const MAGIC: &[u8] = b"btsnoop\0";
+const HEADER_LEN: usize = 16;
+const RECORD_HEADER_LEN: usize = 24;
+
+// Datalink types
+const HCI_UNENCAPSULATED: u32 = 1001;
+const HCI_UART: u32 = 1002;
+
+// HCI packet types as used by H4
+const HCI_COMMAND: u8 = 0x01;
+const HCI_ACL: u8 = 0x02;
+const HCI_EVENT: u8 = 0x04;
+
+// ACL packet boundary flag of continuing fragments
+const ACL_CONTINUATION: u16 = 0b01;
+const L2CAP_ATT_CID: u16 = 0x0004;
+
+// ATT opcodes
+const ATT_READ_BY_TYPE_RESPONSE: u8 = 0x09;
+const ATT_WRITE_REQUEST: u8 = 0x12;
+const ATT_WRITE_COMMAND: u8 = 0x52;
+const ATT_NOTIFICATION: u8 = 0x1b;
+const ATT_INDICATION: u8 = 0x1d;
+
+#[derive(Debug)]
+pub enum BtsnoopError {
+    Io(io::Error),
+    NotBtsnoop,
+    UnsupportedDatalink(u32),
+    // Record number, counted from 1
+    Truncated(usize),
+}
+
+impl fmt::Display for BtsnoopError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BtsnoopError::Io(err) => write!(f, "cannot read btsnoop log: {}", err),
+            BtsnoopError::NotBtsnoop => write!(f, "not a btsnoop log"),
+            BtsnoopError::UnsupportedDatalink(datalink) => write!(f, "unsupported btsnoop datalink {}", datalink),
+            BtsnoopError::Truncated(record) => write!(f, "btsnoop record {} is truncated", record),
+        }
+    }
+}
+
+impl std::error::Error for BtsnoopError {}
+
+impl From<io::Error> for BtsnoopError {
+    fn from(err: io::Error) -> Self {
+        BtsnoopError::Io(err)
+    }
+}
+
+// One HCI packet of the log
+#[derive(Debug, Clone)]
+pub struct HciRecord {
+    // Microseconds since midnight, January 1st of year 0
+    pub timestamp: u64,
+    // Controller to host
+    pub received: bool,
+    // H4 packet type, HCI_ACL for data
+    pub packet_type: u8,
+    // Packet without the H4 type byte
+    pub data: Vec<u8>,
+}
+
+pub fn is_btsnoop(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Vec<HciRecord>, BtsnoopError> {
+    if bytes.len() < HEADER_LEN || !is_btsnoop(bytes) {
+        return Err(BtsnoopError::NotBtsnoop);
+    }
+    let datalink = read_u32(bytes, 12);
+    if datalink != HCI_UNENCAPSULATED && datalink != HCI_UART {
+        return Err(BtsnoopError::UnsupportedDatalink(datalink));
+    }
+    let mut records = Vec::new();
+    let mut at = HEADER_LEN;
+    while at < bytes.len() {
+        let number = records.len() + 1;
+        let header = bytes.get(at..at + RECORD_HEADER_LEN).ok_or(BtsnoopError::Truncated(number))?;
+        let included_len = read_u32(header, 4) as usize;
+        let flags = read_u32(header, 8);
+        let timestamp = u64::from_be_bytes(header[16..24].try_into().unwrap());
+        at += RECORD_HEADER_LEN;
+        let data = bytes.get(at..at + included_len).ok_or(BtsnoopError::Truncated(number))?;
+        at += included_len;
+        let received = flags & 0x01 != 0;
+        let (packet_type, data) = if datalink == HCI_UART {
+            match data.split_first() {
+                Some((&packet_type, data)) => (packet_type, data),
+                None => continue,
+            }
+        } else if flags & 0x02 != 0 {
+            (if received { HCI_EVENT } else { HCI_COMMAND }, data)
+        } else {
+            (HCI_ACL, data)
+        };
+        records.push(HciRecord { timestamp, received, packet_type, data: data.to_vec() });
+    }
+    Ok(records)
+}
+
+// Instax frames found in a btsnoop log, timed from the first record
+// Writes to INSTAX_WRITE_UUID become SENT frames, notifications from
+// INSTAX_NOTIFY_UUID RECV frames. Frames split over several ATT packets are
+// joined using their size field.
+pub fn import(bytes: &[u8]) -> Result<Recording, BtsnoopError> {
+    let records = parse(bytes)?;
+    let mut importer = Importer::default();
+    let start = records.first().map_or(0, |record| record.timestamp);
+    for record in records.iter().filter(|record| record.packet_type == HCI_ACL) {
+        let time = Duration::from_micros(record.timestamp.saturating_sub(start));
+        importer.acl(time, record.received, &record.data);
+    }
+    Ok(Recording { frames: importer.frames })
+}
+
+pub fn load(path: &Path) -> Result<Recording, BtsnoopError> {
+    import(&fs::read(path)?)
+}
+
+#[derive(Default)]
+struct Importer {
+    // Incomplete L2CAP frames by connection handle and direction
+    l2cap: HashMap<(u16, bool), Vec<u8>>,
+    // Characteristic UUIDs by connection handle and value handle
+    characteristics: HashMap<(u16, u16), Uuid>,
+    // Incomplete Instax frames by direction
+    pending: HashMap<bool, (Duration, Vec<u8>)>,
+    frames: Vec<Frame>,
+}
+
+impl Importer {
+    fn acl(&mut self, time: Duration, received: bool, data: &[u8]) {
+        if data.len() < 4 {
+            return;
+        }
+        let handle_flags = u16::from_le_bytes([data[0], data[1]]);
+        let connection = handle_flags & 0x0fff;
+        let fragment = &data[4..];
+        let key = (connection, received);
+        if (handle_flags >> 12) & 0b11 == ACL_CONTINUATION {
+            match self.l2cap.get_mut(&key) {
+                Some(buffer) => buffer.extend(fragment),
+                None => return,
+            }
+        } else {
+            self.l2cap.insert(key, fragment.to_vec());
+        }
+        let buffer = &self.l2cap[&key];
+        if buffer.len() < 4 {
+            return;
+        }
+        let length = u16::from_le_bytes([buffer[0], buffer[1]]) as usize;
+        let cid = u16::from_le_bytes([buffer[2], buffer[3]]);
+        if buffer.len() < 4 + length {
+            return;
+        }
+        let l2cap = self.l2cap.remove(&key).unwrap();
+        if cid == L2CAP_ATT_CID {
+            self.att(time, connection, &l2cap[4..4 + length]);
+        }
+    }
+
+    fn att(&mut self, time: Duration, connection: u16, pdu: &[u8]) {
+        let Some((&opcode, params)) = pdu.split_first() else { return };
+        match opcode {
+            ATT_READ_BY_TYPE_RESPONSE => self.characteristic_declarations(connection, params),
+            ATT_WRITE_REQUEST | ATT_WRITE_COMMAND if params.len() > 2 => {
+                self.value(time, connection, FrameDirection::Sent, params);
+            }
+            ATT_NOTIFICATION | ATT_INDICATION if params.len() > 2 => {
+                self.value(time, connection, FrameDirection::Received, params);
+            }
+            _ => {}
+        }
+    }
+
+    // Characteristic discovery: entries of handle(2) + properties(1) + value handle(2) + UUID
+    fn characteristic_declarations(&mut self, connection: u16, params: &[u8]) {
+        let Some((&entry_len, entries)) = params.split_first() else { return };
+        if entry_len != 7 && entry_len != 21 {
+            return;
+        }
+        for entry in entries.chunks_exact(entry_len as usize) {
+            let value_handle = u16::from_le_bytes([entry[3], entry[4]]);
+            let uuid = match entry_len {
+                7 => Uuid::from_u16(u16::from_le_bytes([entry[5], entry[6]])),
+                _ => Uuid::from_u128(u128::from_le_bytes(entry[5..21].try_into().unwrap())),
+            };
+            self.characteristics.insert((connection, value_handle), uuid);
+        }
+    }
+
+    fn value(&mut self, time: Duration, connection: u16, direction: FrameDirection, params: &[u8]) {
+        let handle = u16::from_le_bytes([params[0], params[1]]);
+        let value = &params[2..];
+        let (uuid, magic) = match direction {
+            FrameDirection::Sent => (INSTAX_WRITE_UUID, b"Ab"),
+            FrameDirection::Received => (INSTAX_NOTIFY_UUID, b"aB"),
+        };
+        match self.characteristics.get(&(connection, handle)) {
+            Some(&known) if known != uuid => return,
+            Some(_) => {}
+            // Apps cache the GATT database, so discovery is often missing
+            // from the log. Learn the handle from the first Instax frame.
+            None if value.starts_with(magic) => { self.characteristics.insert((connection, handle), uuid); }
+            None => return,
+        }
+        self.reassemble(time, direction, value);
+    }
+
+    fn reassemble(&mut self, time: Duration, direction: FrameDirection, value: &[u8]) {
+        let key = direction == FrameDirection::Received;
+        let (start, mut buffer) = self.pending.remove(&key).unwrap_or((time, Vec::new()));
+        buffer.extend(value);
+        loop {
+            match packet::read_u16(&buffer, 2).map(usize::from) {
+                // Too short to tell yet
+                None => break,
+                Some(size) if size < 7 || !buffer.starts_with(b"Ab") && !buffer.starts_with(b"aB") => {
+                    // Not a frame start, keep it as is so the timeline flags it
+                    self.frames.push(Frame { time: start, direction, data: buffer });
+                    return;
+                }
+                Some(size) if buffer.len() >= size => {
+                    let rest = buffer.split_off(size);
+                    self.frames.push(Frame { time: start, direction, data: buffer });
+                    buffer = rest;
+                    if buffer.is_empty() {
+                        return;
+                    }
+                }
+                Some(_) => break,
+            }
+        }
+        self.pending.insert(key, (start, buffer));
+    }
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap())
+}
@@ -0,0 +1,839 @@
+use std::error::Error;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{self, Arc};
+use std::time::Duration;
+use bluer::{Address, Device, Uuid};
+use chrono::prelude::*;
+use futures::{FutureExt, Stream};
+use num_traits::FromPrimitive;
+use tokio::sync::{broadcast, Mutex};
+use tokio::time::{sleep, timeout, Instant};
+use tracing::{debug, info, instrument, trace, warn};
+
+use crate::*;
+use crate::capabilities::Capabilities;
+use crate::codec::Decode;
+use crate::connection::{ConnectionState, ReconnectConfig};
+use crate::discovery::{self, ConnectOptions};
+use crate::keepalive::{Keepalive, KeepaliveConfig};
+use crate::packet::{self, Packet};
+use crate::profile::Profile;
+use crate::recording::{FrameDirection, SessionRecorder};
+use crate::transport::{BleTransport, Transport};
+use crate::upload::UploadBackend;
+
+// UART-like GATT service
+// Commands are sent to INSTAX_WRITE_UUID characteristic
+// Responses are read from INSTAX_NOTIFY_UUID characteristic
+// Reference: https://github.com/jpwsutton/instax_api/issues/21#issuecomment-770462168
+pub const INSTAX_SERVICE_UUID: Uuid = Uuid::from_u128(0x70954782_2d83_473d_9e5f_81e1d02d5273);
+pub const INSTAX_WRITE_UUID: Uuid = Uuid::from_u128(0x70954783_2d83_473d_9e5f_81e1d02d5273);
+pub const INSTAX_NOTIFY_UUID: Uuid = Uuid::from_u128(0x70954784_2d83_473d_9e5f_81e1d02d5273);
+
+// Auto sleep timeout used while a long transfer is running
+const STAY_AWAKE_MINUTES: u8 = 30;
+
+// How long a command waits for its reply
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub enum CameraError {
+    Bluetooth(bluer::Error),
+    NoResponse(SID),
+    Unsupported(String),
+    Decode(DecodeError),
+    Device(CameraErrorType),
+    // Reply statuses, see ResponseStatus
+    Busy(SID),
+    NoData(SID),
+    InvalidParameter(SID),
+    Rejected(SID, ResponseStatus),
+    // A setting read back with another value than was written
+    NotApplied { setting: ReadWriteSettingType, written: u32, read: u32 },
+    Timeout,
+    NotFound(String),
+    ConnectionFailed(String),
+    Disconnected,
+    // The connection dropped in the middle of a multi-packet transfer
+    TransferInterrupted(SID),
+    // The auto upload queue holds photos from before an interval series
+    PhotosQueued { queued: usize, shots: usize },
+    // A replayed session diverged from its recording
+    Replay(String),
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CameraError::Bluetooth(err) => write!(f, "bluetooth error: {}", err),
+            CameraError::NoResponse(sid) => write!(f, "no response to {:?}", sid),
+            CameraError::Unsupported(function) => write!(f, "{} is not supported by this device", function),
+            CameraError::Decode(err) => write!(f, "cannot decode response: {}", err),
+            CameraError::Device(error) => write!(f, "camera error: {:?}", error),
+            CameraError::Busy(sid) => write!(f, "camera is busy, {:?} refused", sid),
+            CameraError::NoData(sid) => write!(f, "no data available for {:?}", sid),
+            CameraError::InvalidParameter(sid) => write!(f, "invalid parameter for {:?}", sid),
+            CameraError::Rejected(sid, status) => write!(f, "{:?} rejected with status 0x{:02x}", sid, status.code()),
+            CameraError::NotApplied { setting, written, read } => write!(f, "{:?} reads {} after writing {}", setting, read, written),
+            CameraError::Timeout => write!(f, "timed out waiting for camera"),
+            CameraError::NotFound(reason) => write!(f, "{}", reason),
+            CameraError::ConnectionFailed(reason) => write!(f, "cannot connect to camera: {}", reason),
+            CameraError::Disconnected => write!(f, "camera disconnected"),
+            CameraError::TransferInterrupted(sid) => write!(f, "connection lost during {:?} transfer", sid),
+            CameraError::PhotosQueued { queued, shots } => {
+                write!(f, "{} photos queued for {} interval shots, download the other photos first", queued, shots)
+            }
+            CameraError::Replay(reason) => write!(f, "replay diverged: {}", reason),
+        }
+    }
+}
+
+impl Error for CameraError {}
+
+impl From<bluer::Error> for CameraError {
+    fn from(err: bluer::Error) -> Self {
+        CameraError::Bluetooth(err)
+    }
+}
+
+impl From<DecodeError> for CameraError {
+    fn from(err: DecodeError) -> Self {
+        CameraError::Decode(err)
+    }
+}
+
+impl CameraError {
+    // Error for a reply status, None if the request succeeded
+    pub fn from_status(sid: SID, status: ResponseStatus) -> Option<Self> {
+        match status {
+            ResponseStatus::OK => None,
+            ResponseStatus::BUSY => Some(CameraError::Busy(sid)),
+            ResponseStatus::NO_DATA => Some(CameraError::NoData(sid)),
+            ResponseStatus::INVALID_PARAMETER => Some(CameraError::InvalidParameter(sid)),
+            ResponseStatus::NOT_SUPPORTED => Some(CameraError::Unsupported(format!("{:?}", sid))),
+            status => Some(CameraError::Rejected(sid, status)),
+        }
+    }
+}
+
+// User's auto sleep timeout, saved while the camera is kept awake
+pub struct StayAwake {
+    previous: u32,
+}
+
+impl StayAwake {
+    // Only a timeout shorter than the transfer needs is raised, 0 never sleeps
+    fn overrides(&self) -> bool {
+        self.previous != 0 && self.previous < STAY_AWAKE_MINUTES as u32
+    }
+}
+
+// Decoded header at debug level, every field and the raw bytes at trace level
+fn trace_frame(event: &str, data: &[u8]) {
+    if tracing::enabled!(tracing::Level::TRACE) {
+        trace!(raw = %packet::hex(data), "{} {}", event, packet::describe(data));
+    } else if tracing::enabled!(tracing::Level::DEBUG) {
+        let direction = packet::read_u16(data, 0).and_then(Direction::from_u16);
+        let sid = packet::read_u16(data, 4).and_then(SID::from_u16);
+        debug!(?direction, ?sid, size = data.len(), "{}", event);
+    }
+}
+
+// Transport to the camera, plus the recorder when the session is recorded
+pub(crate) struct Link {
+    transport: Box<dyn Transport>,
+    recorder: Option<SessionRecorder>,
+}
+
+impl Link {
+    pub(crate) async fn send_data(&mut self, data: Vec<u8>) -> Result<(), CameraError> {
+        trace_frame("send", &data);
+        if let Some(recorder) = &self.recorder {
+            recorder.record(FrameDirection::Sent, &data);
+        }
+        self.transport.send(data).await
+    }
+
+    pub(crate) async fn receive_data(&mut self) -> Option<Vec<u8>> {
+        let data = self.transport.receive().await?;
+        trace_frame("receive", &data);
+        if let Some(recorder) = &self.recorder {
+            recorder.record(FrameDirection::Received, &data);
+        }
+        Some(data)
+    }
+
+    // Drop frames already received that nobody waits for any more, e.g. a
+    // reply that arrived after its request timed out
+    pub(crate) fn drain(&mut self) {
+        while let Some(Some(data)) = self.receive_data().now_or_never() {
+            debug!(frame = %packet::describe(&data), "dropping stale frame");
+        }
+    }
+}
+
+// State shared between a Camera and its keepalive task
+// Holding the link lock gives exclusive use of the camera for a request
+pub(crate) struct Shared {
+    pub(crate) link: Mutex<Link>,
+    last_activity: sync::Mutex<Instant>,
+    events: broadcast::Sender<ConnectionState>,
+}
+
+impl Shared {
+    pub(crate) fn set_state(&self, state: ConnectionState) {
+        info!(?state, "connection state changed");
+        // No subscribers is fine
+        let _ = self.events.send(state);
+    }
+
+    pub(crate) fn last_activity(&self) -> Instant {
+        *self.last_activity.lock().unwrap()
+    }
+
+    pub(crate) fn touch(&self) {
+        *self.last_activity.lock().unwrap() = Instant::now();
+    }
+}
+
+pub struct Camera {
+    // None for cameras driven through Camera::from_transport
+    device: Option<Device>,
+    address: Address,
+    recorder: Option<SessionRecorder>,
+    shared: Arc<Shared>,
+    capabilities: Option<Capabilities>,
+    keepalive: Option<Keepalive>,
+    reconnect_config: ReconnectConfig,
+    reconnecting: bool,
+    // Multi-packet transfer in progress, failed rather than resumed on reconnect
+    transfer: Option<SID>,
+}
+
+impl Camera {
+    pub async fn new() -> Result<Self, CameraError> {
+        Self::connect(&ConnectOptions::default()).await
+    }
+
+    // Scan for the selected camera and connect to it
+    pub async fn connect(options: &ConnectOptions) -> Result<Self, CameraError> {
+        let session = bluer::Session::new().await?;
+        let adapter = discovery::open_adapter(&session, options.adapter.as_deref()).await?;
+        let device = discovery::find_camera(&adapter, &options.device, options.scan_timeout).await?;
+        if options.pair {
+            discovery::pair(&session, &device).await?;
+        }
+        Self::from_device(device, options.record.clone()).await
+    }
+
+    // Connect to a camera found by discovery::scan or discovery::find_camera
+    // Every frame is written to the recorder if one is given
+    pub async fn from_device(device: Device, recorder: Option<SessionRecorder>) -> Result<Self, CameraError> {
+        let transport = Self::open_transport(&device).await?;
+        let address = device.address();
+        let mut camera = Self::with_link(Link { transport: Box::new(transport), recorder: recorder.clone() }, Some(device), address);
+        camera.recorder = recorder;
+        camera.handshake().await?;
+        camera.shared.set_state(ConnectionState::Connected);
+        camera.start_keepalive(KeepaliveConfig::default());
+        Ok(camera)
+    }
+
+    // Drive a camera through any transport, e.g. a recording::ReplayTransport
+    // No keepalive is started and the camera cannot reconnect
+    pub async fn from_transport(transport: Box<dyn Transport>) -> Result<Self, CameraError> {
+        let mut camera = Self::with_link(Link { transport, recorder: None }, None, Address::any());
+        camera.handshake().await?;
+        camera.shared.set_state(ConnectionState::Connected);
+        Ok(camera)
+    }
+
+    fn with_link(link: Link, device: Option<Device>, address: Address) -> Self {
+        let (events, _) = broadcast::channel(16);
+        let shared = Arc::new(Shared { link: Mutex::new(link), last_activity: sync::Mutex::new(Instant::now()), events });
+        Self {
+            device,
+            address,
+            recorder: None,
+            shared,
+            capabilities: None,
+            keepalive: None,
+            reconnect_config: ReconnectConfig::default(),
+            reconnecting: false,
+            transfer: None,
+        }
+    }
+
+    // Connect the device and subscribe to INSTAX_NOTIFY_UUID
+    async fn open_transport(device: &Device) -> Result<BleTransport, CameraError> {
+        if !device.is_connected().await? {
+            device.connect().await?;
+        }
+        if !device.is_connected().await? {
+            return Err(CameraError::ConnectionFailed("device did not connect".to_string()));
+        }
+        info!(address = %device.address(), "connected to Instax camera");
+        let mut instax_service = None;
+        let mut write_characteristic = None;
+        let mut notify_characteristic = None;
+        for service in device.services().await? {
+            if service.uuid().await? == INSTAX_SERVICE_UUID {
+                instax_service = Some(service);
+            }
+        }
+        let instax_service = instax_service
+            .ok_or(CameraError::ConnectionFailed("Instax BLE service not found".to_string()))?;
+        for characteristic in instax_service.characteristics().await? {
+            match characteristic.uuid().await? {
+                INSTAX_WRITE_UUID => { write_characteristic = Some(characteristic); }
+                INSTAX_NOTIFY_UUID => { notify_characteristic = Some(characteristic); }
+                _ => {}
+            };
+        }
+        let write_characteristic = write_characteristic
+            .ok_or(CameraError::ConnectionFailed("write characteristic not found".to_string()))?;
+        let notify_characteristic = notify_characteristic
+            .ok_or(CameraError::ConnectionFailed("notify characteristic not found".to_string()))?;
+        let notify_stream: Pin<Box<dyn Stream<Item = Vec<u8>> + Send>> = Box::pin(notify_characteristic.notify().await?);
+        Ok(BleTransport::new(write_characteristic, notify_stream))
+    }
+
+    // Session setup run on every (re)connection
+    // A reconnect keeps the capabilities detected before as long as the
+    // version exchange reports the same versions, otherwise detection runs again.
+    async fn handshake(&mut self) -> Result<(), CameraError> {
+        let versions = self.support_function_version_info().await?;
+        if self.capabilities.as_ref().is_none_or(|cached| cached.versions != versions) {
+            self.capabilities = None;
+            self.capabilities = Some(self.detect_capabilities(versions).await?);
+        }
+        if self.capabilities.as_ref().is_some_and(|capabilities| capabilities.supports(SID::TIME_SETTING)) {
+            self.set_timedate().await?;
+        }
+        Ok(())
+    }
+
+    pub fn set_reconnect_config(&mut self, config: ReconnectConfig) {
+        self.reconnect_config = config;
+    }
+
+    // Connection state changes from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<ConnectionState> {
+        self.shared.events.subscribe()
+    }
+
+    // Address::any() for cameras without a Bluetooth device
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    // Reconnect with exponential backoff and restore the session
+    #[instrument(skip(self), fields(address = %self.address))]
+    pub async fn reconnect(&mut self) -> Result<(), CameraError> {
+        self.stop_keepalive();
+        self.reconnecting = true;
+        let result = self.reconnect_with_backoff().await;
+        self.reconnecting = false;
+        match result {
+            Ok(()) => {
+                self.shared.set_state(ConnectionState::Connected);
+                self.start_keepalive(KeepaliveConfig::default());
+            }
+            Err(_) => self.shared.set_state(ConnectionState::Failed),
+        }
+        result
+    }
+
+    async fn reconnect_with_backoff(&mut self) -> Result<(), CameraError> {
+        let Some(device) = self.device.clone() else {
+            return Err(CameraError::Disconnected);
+        };
+        let mut last_error = CameraError::Disconnected;
+        for attempt in 1..=self.reconnect_config.max_attempts {
+            self.shared.set_state(ConnectionState::Reconnecting { attempt });
+            sleep(self.reconnect_config.delay(attempt)).await;
+            let _ = device.disconnect().await;
+            match Self::open_transport(&device).await {
+                Ok(transport) => {
+                    *self.shared.link.lock().await = Link { transport: Box::new(transport), recorder: self.recorder.clone() };
+                    match self.handshake().await {
+                        Ok(()) => return Ok(()),
+                        Err(err) => last_error = err,
+                    }
+                }
+                Err(err) => last_error = err,
+            }
+            warn!(attempt, error = %last_error, "reconnect attempt failed");
+        }
+        Err(last_error)
+    }
+
+    // Replaces any running keepalive task
+    pub fn start_keepalive(&mut self, config: KeepaliveConfig) {
+        self.keepalive = Some(Keepalive::spawn(self.shared.clone(), config));
+    }
+
+    pub fn stop_keepalive(&mut self) {
+        self.keepalive = None;
+    }
+
+    pub fn keepalive(&self) -> Option<&Keepalive> {
+        self.keepalive.as_ref()
+    }
+
+    pub async fn send_data(&self, data: Vec<u8>) -> Result<(), CameraError> {
+        self.shared.touch();
+        self.shared.link.lock().await.send_data(data).await
+    }
+
+    pub async fn receive_data(&mut self) -> Option<Vec<u8>> {
+        let data = self.shared.link.lock().await.receive_data().await;
+        self.shared.touch();
+        data
+    }
+
+    pub async fn send_packet(&self, packet: Packet) -> Result<(), CameraError> {
+        let data = packet.pack();
+        self.send_data(data).await
+    }
+
+    pub async fn receive_packet(&mut self) -> Result<Packet, CameraError> {
+        let data = self.receive_data().await.ok_or(CameraError::Disconnected)?;
+        Ok(Packet::unpack(&data)?)
+    }
+
+    // Capabilities detected at connect time
+    pub fn capabilities(&self) -> Option<&Capabilities> {
+        self.capabilities.as_ref()
+    }
+
+    fn check_supported(&self, sid: SID) -> Result<(), CameraError> {
+        match &self.capabilities {
+            Some(capabilities) if !capabilities.supports(sid) => Err(CameraError::Unsupported(format!("{:?}", sid))),
+            _ => Ok(()),
+        }
+    }
+
+    // Send a command the device supports and wait for its response
+    // Late replies to earlier commands are skipped. When the link is gone the
+    // camera is reconnected: commands that never went out and idempotent ones
+    // are sent once more, others fail with Disconnected since the camera may
+    // have run them. Interrupted transfers fail with TransferInterrupted.
+    #[instrument(skip_all, fields(sid = ?packet.sid))]
+    pub async fn request(&mut self, packet: Packet) -> Result<Packet, CameraError> {
+        let sid = packet.sid;
+        self.check_supported(sid)?;
+        if self.keepalive.as_ref().is_some_and(|keepalive| keepalive.is_lost()) {
+            self.recover().await?;
+        }
+        match self.exchange(&packet).await {
+            Err(CameraError::Disconnected) if !sid.is_idempotent() => {
+                self.shared.set_state(ConnectionState::Disconnected);
+                Err(CameraError::Disconnected)
+            }
+            Err(CameraError::Bluetooth(_) | CameraError::Disconnected) => {
+                self.shared.set_state(ConnectionState::Disconnected);
+                self.recover().await?;
+                self.exchange(&packet).await
+            }
+            result => result,
+        }
+    }
+
+    // Bluetooth errors mean the command was not sent, Disconnected that the
+    // link dropped after sending it
+    // Frames left over from earlier requests are dropped before sending, and
+    // replies that do not answer this request are skipped, see packet::answers.
+    async fn exchange(&mut self, packet: &Packet) -> Result<Packet, CameraError> {
+        let request = packet.pack();
+        let mut link = self.shared.link.lock().await;
+        self.shared.touch();
+        link.drain();
+        link.send_data(request.clone()).await?;
+        let receive = async {
+            loop {
+                let data = link.receive_data().await.ok_or(CameraError::Disconnected)?;
+                if packet::answers(&request, &data) {
+                    return Ok::<_, CameraError>(data);
+                }
+                debug!(frame = %packet::describe(&data), "skipping late reply");
+            }
+        };
+        let data = timeout(RESPONSE_TIMEOUT, receive).await.map_err(|_| CameraError::Timeout)??;
+        let response = Packet::unpack(&data)?;
+        self.shared.touch();
+        Ok(response)
+    }
+
+    // Send a command whose reply leads with a ResponseStatus byte, see StatusReply
+    // Any status but OK fails with the matching error.
+    async fn acknowledged(&mut self, packet: Packet) -> Result<Packet, CameraError> {
+        let sid = packet.sid;
+        let response = self.request(packet).await?;
+        match CameraError::from_status(sid, StatusReply::decode(&response.data)?.status) {
+            Some(err) => Err(err),
+            None => Ok(response),
+        }
+    }
+
+    async fn recover(&mut self) -> Result<(), CameraError> {
+        if self.reconnecting {
+            return Err(CameraError::Disconnected);
+        }
+        Box::pin(self.reconnect()).await?;
+        match self.transfer.take() {
+            Some(transfer) => Err(CameraError::TransferInterrupted(transfer)),
+            None => Ok(()),
+        }
+    }
+
+    async fn detect_capabilities(&mut self, versions: SupportFunctionVersionInfo) -> Result<Capabilities, CameraError> {
+        let image_support = match versions.support_img_info_version {
+            0 => None,
+            _ => match self.query_support_function_info(&versions, SupportFunctionInfoType::IMAGE_SUPPORT_INFO).await? {
+                SupportFunctionInfoResponse::ImageSupportInfo(info) => Some(info),
+                _ => None,
+            },
+        };
+        // Device info only names the model, a failure must not fail the connect
+        let device_info = match versions.device_info_version {
+            0 => None,
+            _ => match self.device_info().await {
+                Ok(info) => Some(info),
+                Err(err) => {
+                    warn!(%err, "cannot read device info");
+                    None
+                }
+            },
+        };
+        Ok(Capabilities::new(versions, image_support, device_info.as_ref()))
+    }
+
+    pub async fn support_function_version_info(&mut self) -> Result<SupportFunctionVersionInfo, CameraError> {
+        let response = self.request(Packet::with_sid(SID::SUPPORT_FUNCTION_AND_VERSION_INFO)).await?;
+        Ok(SupportFunctionVersionInfo::decode(&response.data)?)
+    }
+
+    async fn query_support_function_info(&mut self, versions: &SupportFunctionVersionInfo, info_type: SupportFunctionInfoType) -> Result<SupportFunctionInfoResponse, CameraError> {
+        if versions.version_of(info_type) == 0 {
+            return Err(CameraError::Unsupported(format!("{:?}", info_type)));
+        }
+        let response = self.acknowledged(Packet::with_payload(SID::SUPPORT_FUNCTION_INFO, &SupportFunctionInfoRequest { info_type })).await?;
+        Ok(SupportFunctionInfoResponse::decode(&response.data, versions)?)
+    }
+
+    pub async fn support_function_info(&mut self, info_type: SupportFunctionInfoType) -> Result<SupportFunctionInfoResponse, CameraError> {
+        let versions = match &self.capabilities {
+            Some(capabilities) => capabilities.versions.clone(),
+            None => self.support_function_version_info().await?,
+        };
+        self.query_support_function_info(&versions, info_type).await
+    }
+
+    pub async fn device_info(&mut self) -> Result<DeviceInfo, CameraError> {
+        let mut info = DeviceInfo::default();
+        for info_type in DeviceInfoType::ALL {
+            let response = self.acknowledged(Packet::with_payload(SID::DEVICE_INFO_SERVICE, &DeviceInfoRequest { info_type })).await?;
+            info.update_from_bytes(&response.data)?;
+        }
+        info.identify_information = self.request(Packet::with_sid(SID::IDENTIFY_INFORMATION)).await?.data;
+        Ok(info)
+    }
+
+    pub async fn parameter_read(&mut self, setting: ReadWriteSettingType) -> Result<ParameterReadWriteResponse, CameraError> {
+        let request = ParameterReadWriteRequest { setting, mode: ReadWriteSettingMode::GET_CURRENT_SETTING, value: 0 };
+        let response = self.acknowledged(Packet::with_payload(SID::PARAMETER_RW, &request)).await?;
+        Ok(ParameterReadWriteResponse::decode(&response.data)?)
+    }
+
+    // Settings are written through CAMERA_SETTINGS with the same
+    // setting + little endian value layout used by PARAMETER_RW
+    // AUTO_POWER_OFF is read-only there and goes through AUTO_SLEEP_SETTINGS
+    // The CAMERA_SETTINGS layout is inferred from PARAMETER_RW rather than
+    // captured, so every write is confirmed by reading the setting back.
+    pub async fn parameter_write(&mut self, setting: ReadWriteSettingType, value: u32) -> Result<(), CameraError> {
+        if setting == ReadWriteSettingType::AUTO_POWER_OFF {
+            let minutes = u8::try_from(value).map_err(|_| CameraError::InvalidParameter(SID::AUTO_SLEEP_SETTINGS))?;
+            self.set_auto_sleep(minutes).await?;
+        } else {
+            self.acknowledged(Packet::with_payload(SID::CAMERA_SETTINGS, &CameraSettingsRequest { setting, value })).await?;
+        }
+        let read = self.parameter_read(setting).await?.value;
+        if read != value {
+            return Err(CameraError::NotApplied { setting, written: value, read });
+        }
+        Ok(())
+    }
+
+    pub async fn read_profile(&mut self) -> Result<Profile, CameraError> {
+        let mut profile = Profile::new();
+        for setting in ReadWriteSettingType::ALL {
+            let response = self.parameter_read(setting).await?;
+            profile.settings.insert(setting, response.value);
+        }
+        Ok(profile)
+    }
+
+    // Write only the settings that differ from the camera's current values
+    pub async fn apply_profile(&mut self, profile: &Profile) -> Result<(), CameraError> {
+        let current = self.read_profile().await?;
+        for diff in current.diff(profile) {
+            let Some(value) = diff.right else { continue };
+            info!("writing {}", diff);
+            self.parameter_write(diff.setting, value).await?;
+        }
+        Ok(())
+    }
+
+    // Auto sleep timeout in minutes
+    pub async fn set_auto_sleep(&mut self, minutes: u8) -> Result<(), CameraError> {
+        self.acknowledged(Packet::with_payload(SID::AUTO_SLEEP_SETTINGS, &AutoSleepRequest { minutes })).await?;
+        Ok(())
+    }
+
+    // The camera may power off before it replies, losing the link after the
+    // command went out is success
+    pub async fn shut_down(&mut self) -> Result<(), CameraError> {
+        match self.acknowledged(Packet::with_sid(SID::SHUT_DOWN)).await {
+            Ok(_) | Err(CameraError::Disconnected) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub async fn reset(&mut self) -> Result<(), CameraError> {
+        match self.acknowledged(Packet::with_sid(SID::RESET)).await {
+            Ok(_) | Err(CameraError::Disconnected) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Extend auto sleep for a long transfer, see restore_auto_sleep
+    pub async fn stay_awake(&mut self) -> Result<StayAwake, CameraError> {
+        let previous = self.parameter_read(ReadWriteSettingType::AUTO_POWER_OFF).await?.value;
+        let stay_awake = StayAwake { previous };
+        if stay_awake.overrides() {
+            self.set_auto_sleep(STAY_AWAKE_MINUTES).await?;
+        }
+        Ok(stay_awake)
+    }
+
+    pub async fn restore_auto_sleep(&mut self, stay_awake: StayAwake) -> Result<(), CameraError> {
+        if stay_awake.overrides() {
+            self.set_auto_sleep(stay_awake.previous as u8).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn set_timedate(&mut self) -> Result<DateTimeResponse, CameraError> {
+        let response = self.acknowledged(Packet::with_payload(SID::TIME_SETTING, &DateTimeRequest::set(Utc::now()))).await?;
+        Ok(DateTimeResponse::decode(&response.data)?)
+    }
+
+    pub async fn check_camera_status(&mut self) -> Result<CameraStatus, CameraError> {
+        let response = self.acknowledged(Packet::with_sid(SID::CHECK_CAMERA_STATUS)).await?;
+        Ok(CameraStatus::decode(&response.data)?)
+    }
+
+    pub async fn execute_camera_command(&mut self, command: CameraCommand) -> Result<(), CameraError> {
+        if command == CameraCommand::PRINT && self.capabilities.as_ref().is_some_and(|c| !c.printer) {
+            return Err(CameraError::Unsupported(format!("{:?}", command)));
+        }
+        self.acknowledged(Packet::with_payload(SID::EXECUTE_CAMERA_COMMAND, &CameraCommandRequest { command })).await?;
+        Ok(())
+    }
+
+    // Poll CHECK_CAMERA_STATUS until the camera is idle
+    // Fails on camera errors or when the camera stays busy past the timeout
+    pub async fn wait_until_ready(&mut self, timeout: Duration) -> Result<CameraStatus, CameraError> {
+        let start = Instant::now();
+        loop {
+            let status = self.check_camera_status().await?;
+            if status.error != CameraErrorType::NO_ERROR {
+                return Err(CameraError::Device(status.error));
+            }
+            if status.is_ready() {
+                return Ok(status);
+            }
+            if start.elapsed() > timeout {
+                return Err(CameraError::Timeout);
+            }
+            sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    // Download one image queued for automatic transfer
+    // Returns None once no more photos are available
+    #[instrument(skip(self))]
+    pub async fn auto_upload_image(&mut self) -> Result<Option<Vec<u8>>, CameraError> {
+        self.transfer = Some(SID::IMAGE_AUTO_UPLOAD_START);
+        let image = self.receive_auto_upload_image().await;
+        self.transfer = None;
+        image
+    }
+
+    async fn receive_auto_upload_image(&mut self) -> Result<Option<Vec<u8>>, CameraError> {
+        match self.acknowledged(Packet::with_sid(SID::IMAGE_AUTO_UPLOAD_INFO)).await {
+            Err(CameraError::NoData(_)) => {
+                debug!("no photo available");
+                return Ok(None);
+            }
+            result => result?,
+        };
+        let response = self.acknowledged(Packet::with_data(SID::IMAGE_AUTO_UPLOAD_START, vec![0;4])).await?;
+        let num_frames = AutoUploadStartResponse::decode(&response.data)?.num_frames;
+        debug!(num_frames, "receiving auto upload image");
+        let mut image = Vec::new();
+        for frame in 0..num_frames {
+            let request = ChunkRequest { index: frame as u32 };
+            let response = self.request(Packet::with_payload(SID::IMAGE_AUTO_UPLOAD_DATA, &request)).await?;
+            // Each frame echoes its frame number before the image chunk
+            image.extend(response.data.get(4..).unwrap_or_default());
+            trace!(frame, "received frame");
+        }
+        self.acknowledged(Packet::with_sid(SID::IMAGE_AUTO_UPLOAD_END)).await?;
+        self.acknowledged(Packet::with_sid(SID::IMAGE_AUTO_UPLOAD_COMPLETE)).await?;
+        Ok(Some(image))
+    }
+
+    // Download every photo queued for automatic transfer
+    pub async fn automatic_photo_download(&mut self) -> Result<Vec<Vec<u8>>, CameraError> {
+        self.auto_upload_images(usize::MAX).await
+    }
+
+    // Download at most `limit` queued photos
+    async fn auto_upload_images(&mut self, limit: usize) -> Result<Vec<Vec<u8>>, CameraError> {
+        let mut images = Vec::new();
+        while images.len() < limit {
+            match self.auto_upload_image().await? {
+                Some(image) => images.push(image),
+                None => break,
+            }
+        }
+        Ok(images)
+    }
+
+    // Receive `size` bytes from the camera using a START / chunk / END SID triple
+    // Chunks are requested by index and echo it before the data
+    #[instrument(skip(self))]
+    async fn receive_upload(&mut self, start: SID, chunk: SID, end: SID, size: u32) -> Result<Vec<u8>, CameraError> {
+        self.transfer = Some(start);
+        let data = self.receive_upload_chunks(start, chunk, end, size).await;
+        self.transfer = None;
+        data
+    }
+
+    async fn receive_upload_chunks(&mut self, start: SID, chunk: SID, end: SID, size: u32) -> Result<Vec<u8>, CameraError> {
+        self.acknowledged(Packet::with_sid(start)).await?;
+        let data = self.receive_chunks(chunk, size).await?;
+        self.acknowledged(Packet::with_sid(end)).await?;
+        Ok(data)
+    }
+
+    async fn receive_chunks(&mut self, chunk: SID, size: u32) -> Result<Vec<u8>, CameraError> {
+        let mut data = Vec::new();
+        let mut index: u32 = 0;
+        while (data.len() as u32) < size {
+            let response = self.request(Packet::with_payload(chunk, &ChunkRequest { index })).await?;
+            match response.data.get(4..) {
+                Some(bytes) if !bytes.is_empty() => data.extend(bytes),
+                _ => return Err(CameraError::NoResponse(chunk)),
+            }
+            index += 1;
+        }
+        Ok(data)
+    }
+
+    // Read one of the camera's usage logs as raw bytes
+    // START replies with the log size, DATA chunks then work like receive_upload
+    #[instrument(skip(self))]
+    pub async fn camera_log(&mut self, log: CameraLogType) -> Result<Vec<u8>, CameraError> {
+        let response = self.acknowledged(Packet::with_sid(log.start_sid())).await?;
+        let start = CameraLogStartResponse::decode(&response.data)?;
+        self.transfer = Some(log.start_sid());
+        let data = self.receive_chunks(log.data_sid(), start.size).await;
+        self.transfer = None;
+        data
+    }
+
+    // Sound print: fetch picture and audio from the camera, publish them through
+    // the backend, hand the resulting URL back to the camera and print it as QR code
+    // The PRINT command code is unverified, see CameraCommand, so the camera
+    // may acknowledge the last step without printing anything.
+    #[instrument(skip_all)]
+    pub async fn sound_print(&mut self, backend: &dyn UploadBackend) -> Result<String, Box<dyn Error>> {
+        let response = self.acknowledged(Packet::with_sid(SID::URL_UPLOAD_INFO)).await?;
+        let info = UrlUploadInfo::decode(&response.data)?;
+        debug!(?info, "url upload info");
+        let stay_awake = self.stay_awake().await?;
+        let picture = self.receive_upload(SID::URL_PICTURE_UPLOAD_START, SID::URL_PICTURE_UPLOAD, SID::URL_PICTURE_UPLOAD_END, info.picture_size).await;
+        let audio = self.receive_upload(SID::URL_AUDIO_UPLOAD_START, SID::URL_AUDIO_UPLOAD, SID::URL_AUDIO_UPLOAD_END, info.audio_size).await;
+        self.restore_auto_sleep(stay_awake).await?;
+        let url = backend.upload(&picture?, &audio?)?;
+        info!(%url, "uploaded sound print");
+        self.acknowledged(Packet::with_payload(SID::URL_UPLOAD_ADDRESS, &UrlUploadAddress { url: url.clone() })).await?;
+        self.acknowledged(Packet::with_sid(SID::URL_UPLOAD_DATA_COMPLETE)).await?;
+        self.wait_until_ready(Duration::from_secs(30)).await?;
+        self.execute_camera_command(CameraCommand::PRINT).await?;
+        Ok(url)
+    }
+
+    pub async fn interval_record_status(&mut self) -> Result<IntervalRecordStatus, CameraError> {
+        let response = self.acknowledged(Packet::with_sid(SID::INTERVAL_RECORD_STATUS)).await?;
+        Ok(IntervalRecordStatus::decode(&response.data)?)
+    }
+
+    // Configure the shoot time for the given mode, then start shooting
+    pub async fn interval_record_start(&mut self, mode: IntervalRecordMode, shoot_time: u32, shots: u16) -> Result<(), CameraError> {
+        self.check_supported(SID::INTERVAL_RECORD_START)?;
+        self.parameter_write(mode.shoot_time_setting(), shoot_time).await?;
+        self.acknowledged(Packet::with_payload(SID::INTERVAL_RECORD_START, &IntervalRecordStartRequest { mode, shots })).await?;
+        Ok(())
+    }
+
+    pub async fn interval_record_stop(&mut self) -> Result<(), CameraError> {
+        self.acknowledged(Packet::with_sid(SID::INTERVAL_RECORD_STOP)).await?;
+        Ok(())
+    }
+
+    // Download the recorded series, keeping the camera awake meanwhile
+    // The auto upload queue does not tell interval shots from other photos and
+    // hands out the oldest first, so photos queued before the series would take
+    // the place of its shots. Refuses to start unless the queue holds no more
+    // photos than INTERVAL_RECORD_STATUS reports taken.
+    pub async fn interval_record_download(&mut self) -> Result<Vec<Vec<u8>>, CameraError> {
+        self.wait_until_ready(Duration::from_secs(30)).await?;
+        let shots = self.interval_record_status().await?.shots_taken as usize;
+        let queued = match self.support_function_info(SupportFunctionInfoType::CAMERA_FUNCTION_INFO).await? {
+            SupportFunctionInfoResponse::CameraFunctionInfo(info) => info.auto_image_transfer_count as usize,
+            _ => return Err(CameraError::NoResponse(SID::SUPPORT_FUNCTION_INFO)),
+        };
+        if queued > shots {
+            return Err(CameraError::PhotosQueued { queued, shots });
+        }
+        let stay_awake = self.stay_awake().await?;
+        let images = self.auto_upload_images(shots).await;
+        self.restore_auto_sleep(stay_awake).await?;
+        images
+    }
+
+    // Capture one live view frame as JPEG
+    #[instrument(skip(self))]
+    pub async fn live_view_image(&mut self) -> Result<Vec<u8>, CameraError> {
+        self.request(Packet::with_type(SID::LIVE_VIEW_START, 0)).await?;
+        // Keep the link until all chunks are in so keepalive cannot interleave
+        let mut link = self.shared.link.lock().await;
+        link.send_data(Packet::with_sid(SID::LIVE_VIEW_RECEIVE).pack()).await?;
+        sleep(Duration::from_millis(600)).await;
+        let mut photo = Vec::<u8>::new();
+        // Receive 3 live-view photo chunks
+        for _ in 0..3 {
+            let data = link.receive_data().await.ok_or(CameraError::NoResponse(SID::LIVE_VIEW_RECEIVE))?;
+            photo.extend(data);
+        };
+        link.send_data(Packet::with_sid(SID::LIVE_VIEW_STOP).pack()).await?;
+        drop(link);
+        self.shared.touch();
+        Ok(photo.get(11..).unwrap_or_default().to_vec())
+    }
+}
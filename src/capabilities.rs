@@ -0,0 +1,55 @@
+use crate::{DeviceInfo, ImageSupportInfo, SupportFunctionInfoType, SupportFunctionVersionInfo, SID};
+
+// Protocol subset supported by the connected device
+// Built at connect time from SUPPORT_FUNCTION_AND_VERSION_INFO, ImageSupportInfo
+// and DEVICE_INFO_SERVICE. A layout version of 0 means the device does not
+// implement that function.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Capabilities {
+    pub model: Option<String>,
+    pub versions: SupportFunctionVersionInfo,
+    pub image_support: Option<ImageSupportInfo>,
+    pub device_info: bool,
+    pub printer: bool,
+    pub camera: bool,
+    pub image_transfer: bool,
+}
+
+impl Capabilities {
+    pub fn new(versions: SupportFunctionVersionInfo, image_support: Option<ImageSupportInfo>, device_info: Option<&DeviceInfo>) -> Self {
+        Capabilities {
+            model: device_info.map(|info| info.model_number.clone()),
+            device_info: versions.device_info_version != 0,
+            printer: versions.printer_func_info_version != 0,
+            camera: versions.camera_func_info_version != 0,
+            image_transfer: image_support.as_ref().is_some_and(|info| info.size > 0),
+            image_support,
+            versions,
+        }
+    }
+
+    pub fn supports_info(&self, info_type: SupportFunctionInfoType) -> bool {
+        self.versions.version_of(info_type) != 0
+    }
+
+    // Whether the device accepts a command, judged by its SID group:
+    // 0x10xx and 0x30xx are printer functions. Of the 0x80xx-0x89xx range only
+    // live view (0x82xx), camera status and commands (0x85xx) and interval
+    // recording (0x89xx) operate the camera itself; settings, time, logs and
+    // uploads share the range but are not gated by the camera function.
+    // Commands moving image data additionally need a reported image format.
+    pub fn supports(&self, sid: SID) -> bool {
+        let group = (sid as u16) >> 8;
+        let needs_printer = matches!(group, 0x10 | 0x30) || matches!(sid, SID::POST_VIEW_PRINT);
+        let needs_camera = matches!(group, 0x82 | 0x85 | 0x89);
+        let needs_image = matches!(group, 0x82 | 0x87 | 0x88) || matches!(sid,
+            SID::URL_PICTURE_UPLOAD_START | SID::URL_PICTURE_UPLOAD | SID::URL_PICTURE_UPLOAD_END);
+        match sid {
+            SID::DEVICE_INFO_SERVICE => self.device_info,
+            _ => (!needs_printer || self.printer)
+                && (!needs_camera || self.camera)
+                && (!needs_image || self.image_transfer),
+        }
+    }
+}
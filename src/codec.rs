@@ -0,0 +1,316 @@
+use num_traits::{FromPrimitive, ToPrimitive};
+
+use crate::{CameraErrorType, DecodeError, ResponseStatus};
+
+// Declarative payload layouts
+// layout! declares a payload struct with the offset, width, byte order and
+// enum mapping of each field next to it, and generates its Decode and Encode
+// impls from that, e.g.
+//
+//   layout! {
+//       pub struct UrlUploadInfo {
+//           pub status: ResponseStatus = Status(0),
+//           pub picture_size: u32 = U32Be(2),
+//           pub audio_size: u32 = U32Be(6),
+//       }
+//   }
+//
+// Offsets count from the first payload byte after the SID.
+
+// Payload of a request or response, the bytes between SID and checksum
+// Client, timeline and tests build and parse payloads through the same impls.
+pub trait Decode: Sized {
+    fn decode(bytes: &[u8]) -> Result<Self, DecodeError>;
+}
+
+pub trait Encode {
+    fn encode(&self) -> Vec<u8>;
+}
+
+// A payload struct with a generated codec
+pub trait Layout: Decode + Encode {
+    // Bytes every payload of this type has, optional fields excluded
+    fn min_len() -> usize;
+}
+
+// How one field of type T is stored
+// read is only called on payloads of at least end() bytes
+pub trait Field<T> {
+    fn end(&self) -> usize;
+    fn read(&self, bytes: &[u8]) -> Result<T, DecodeError>;
+    fn write(&self, bytes: &mut Vec<u8>, value: &T);
+}
+
+// Grow bytes to hold a field ending at end
+fn reserve(bytes: &mut Vec<u8>, end: usize) -> &mut Vec<u8> {
+    if bytes.len() < end {
+        bytes.resize(end, 0);
+    }
+    bytes
+}
+
+pub struct U8(pub usize);
+
+impl Field<u8> for U8 {
+    fn end(&self) -> usize {
+        self.0 + 1
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<u8, DecodeError> {
+        Ok(bytes[self.0])
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>, value: &u8) {
+        reserve(bytes, self.end())[self.0] = *value;
+    }
+}
+
+// Any non-zero byte is true, written as 1
+pub struct Bool(pub usize);
+
+impl Field<bool> for Bool {
+    fn end(&self) -> usize {
+        self.0 + 1
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<bool, DecodeError> {
+        Ok(bytes[self.0] != 0)
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>, value: &bool) {
+        reserve(bytes, self.end())[self.0] = *value as u8;
+    }
+}
+
+// Bits of one byte selected by a mask, shifted down to bit 0
+pub struct Bits(pub usize, pub u8);
+
+impl Field<u8> for Bits {
+    fn end(&self) -> usize {
+        self.0 + 1
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<u8, DecodeError> {
+        Ok((bytes[self.0] & self.1) >> self.1.trailing_zeros())
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>, value: &u8) {
+        let byte = &mut reserve(bytes, self.end())[self.0];
+        *byte = (*byte & !self.1) | ((value << self.1.trailing_zeros()) & self.1);
+    }
+}
+
+// Set when any bit of the mask is set
+pub struct Flag(pub usize, pub u8);
+
+impl Field<bool> for Flag {
+    fn end(&self) -> usize {
+        self.0 + 1
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<bool, DecodeError> {
+        Ok(bytes[self.0] & self.1 != 0)
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>, value: &bool) {
+        let byte = &mut reserve(bytes, self.end())[self.0];
+        *byte = if *value { *byte | self.1 } else { *byte & !self.1 };
+    }
+}
+
+macro_rules! int_field {
+    ($name:ident, $ty:ty, $from:ident, $to:ident) => {
+        pub struct $name(pub usize);
+
+        impl Field<$ty> for $name {
+            fn end(&self) -> usize {
+                self.0 + std::mem::size_of::<$ty>()
+            }
+
+            fn read(&self, bytes: &[u8]) -> Result<$ty, DecodeError> {
+                Ok(<$ty>::$from(bytes[self.0..self.end()].try_into().unwrap()))
+            }
+
+            fn write(&self, bytes: &mut Vec<u8>, value: &$ty) {
+                let end = self.end();
+                reserve(bytes, end)[self.0..end].copy_from_slice(&value.$to());
+            }
+        }
+    };
+}
+
+int_field!(U16Be, u16, from_be_bytes, to_be_bytes);
+int_field!(U16Le, u16, from_le_bytes, to_le_bytes);
+int_field!(U32Be, u32, from_be_bytes, to_be_bytes);
+int_field!(U32Le, u32, from_le_bytes, to_le_bytes);
+
+// Enum stored in one byte, unknown values fail to decode
+// The label names the field in the error, e.g. "info type"
+pub struct Enum(pub usize, pub &'static str);
+
+impl<T: FromPrimitive + ToPrimitive> Field<T> for Enum {
+    fn end(&self) -> usize {
+        self.0 + 1
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<T, DecodeError> {
+        T::from_u8(bytes[self.0]).ok_or(DecodeError::InvalidValue { field: self.1, at: self.0, value: bytes[self.0] })
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>, value: &T) {
+        reserve(bytes, self.0 + 1)[self.0] = value.to_u8().unwrap_or(u8::MAX);
+    }
+}
+
+// Enum stored in one byte, unknown values decode to the fallback
+pub struct EnumOr<T>(pub usize, pub T);
+
+impl<T: FromPrimitive + ToPrimitive + Copy> Field<T> for EnumOr<T> {
+    fn end(&self) -> usize {
+        self.0 + 1
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<T, DecodeError> {
+        Ok(T::from_u8(bytes[self.0]).unwrap_or(self.1))
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>, value: &T) {
+        reserve(bytes, self.0 + 1)[self.0] = value.to_u8().unwrap_or(u8::MAX);
+    }
+}
+
+// Signed big endian camera error code, see CameraErrorType::from_code
+pub struct ErrorCode(pub usize);
+
+impl Field<CameraErrorType> for ErrorCode {
+    fn end(&self) -> usize {
+        self.0 + 2
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<CameraErrorType, DecodeError> {
+        Ok(CameraErrorType::from_code(i16::from_be_bytes([bytes[self.0], bytes[self.0 + 1]])))
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>, value: &CameraErrorType) {
+        U16Be(self.0).write(bytes, &(*value as i16 as u16))
+    }
+}
+
+// Result code leading a reply, see StatusReply
+pub struct Status(pub usize);
+
+impl Field<ResponseStatus> for Status {
+    fn end(&self) -> usize {
+        self.0 + 1
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<ResponseStatus, DecodeError> {
+        Ok(ResponseStatus::from_code(bytes[self.0]))
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>, value: &ResponseStatus) {
+        reserve(bytes, self.end())[self.0] = value.code();
+    }
+}
+
+// Field only present in longer payloads, e.g. in newer layout versions
+pub struct Optional<F>(pub F);
+
+impl<T, F: Field<T>> Field<Option<T>> for Optional<F> {
+    fn end(&self) -> usize {
+        0
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<Option<T>, DecodeError> {
+        match bytes.len() >= self.0.end() {
+            true => self.0.read(bytes).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>, value: &Option<T>) {
+        if let Some(value) = value {
+            self.0.write(bytes, value);
+        }
+    }
+}
+
+// The rest of the payload from the offset on
+pub struct Bytes(pub usize);
+
+impl Field<Vec<u8>> for Bytes {
+    fn end(&self) -> usize {
+        self.0
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        Ok(bytes[self.0..].to_vec())
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>, value: &Vec<u8>) {
+        reserve(bytes, self.0).truncate(self.0);
+        bytes.extend(value);
+    }
+}
+
+// The rest of the payload as text, invalid UTF-8 is replaced
+pub struct Text(pub usize);
+
+impl Field<String> for Text {
+    fn end(&self) -> usize {
+        self.0
+    }
+
+    fn read(&self, bytes: &[u8]) -> Result<String, DecodeError> {
+        Ok(String::from_utf8_lossy(&bytes[self.0..]).into_owned())
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>, value: &String) {
+        Bytes(self.0).write(bytes, &value.clone().into_bytes())
+    }
+}
+
+macro_rules! layout {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_attr:meta])*
+                $field_vis:vis $field:ident : $ty:ty = $spec:expr
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name {
+            $(
+                $(#[$field_attr])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        impl $crate::codec::Layout for $name {
+            fn min_len() -> usize {
+                0usize $(.max($crate::codec::Field::<$ty>::end(&$spec)))*
+            }
+        }
+
+        impl $crate::codec::Decode for $name {
+            fn decode(bytes: &[u8]) -> Result<Self, $crate::DecodeError> {
+                $crate::check_len(bytes, <Self as $crate::codec::Layout>::min_len())?;
+                Ok($name {
+                    $($field: $crate::codec::Field::<$ty>::read(&$spec, bytes)?,)*
+                })
+            }
+        }
+
+        impl $crate::codec::Encode for $name {
+            fn encode(&self) -> Vec<u8> {
+                let mut bytes = vec![0; <Self as $crate::codec::Layout>::min_len()];
+                $($crate::codec::Field::<$ty>::write(&$spec, &mut bytes, &self.$field);)*
+                bytes
+            }
+        }
+    };
+}
+
+pub(crate) use layout;
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+// Connection state changes, broadcast by Camera::subscribe
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConnectionState {
+    Connected,
+    // Notify stream ended or keepalive replies stopped
+    Disconnected,
+    Reconnecting { attempt: u32 },
+    // Reconnection gave up after ReconnectConfig::max_attempts
+    Failed,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        ReconnectConfig {
+            max_attempts: 5,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ReconnectConfig {
+    // Exponential backoff before the given attempt, starting at 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
@@ -0,0 +1,239 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use num_traits::FromPrimitive;
+use tokio::time::{timeout_at, Instant};
+
+use crate::camera::{Camera, CameraError};
+use crate::packet;
+use crate::recording::{Frame, FrameDirection};
+use crate::timeline::Annotator;
+use crate::SID;
+
+// Interactive console sending hand-built packets, for exploring the protocol
+// Every frame sent and received is printed annotated like a timeline entry.
+// Scripts are files of console lines, # starts a comment.
+
+pub const HELP: &str = "<sid> [<hex payload>]   send a packet, e.g. check_camera_status, 0x0002 04, 8401 00000001
+                         the SID is a name or any hex code, the payload hex bytes, spaces allowed
+sids                     list known SIDs
+history                  list packets sent so far
+!<n> | !!                send history entry n, or the last one, again
+save <file>              save the history as a script
+run <file>               run a script
+help                     show this help
+quit                     leave the console";
+
+// One console line
+#[derive(Debug, PartialEq)]
+pub enum ConsoleCommand {
+    // Any u16 SID code, names are resolved while parsing
+    Send { sid: u16, payload: Vec<u8> },
+    Sids,
+    History,
+    // History entry counted from 1, None for the last one
+    Repeat(Option<usize>),
+    Save(String),
+    Run(String),
+    Help,
+    Quit,
+    Empty,
+}
+
+impl FromStr for ConsoleCommand {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let (word, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let argument = |usage: &str| match rest {
+            "" => Err(format!("usage: {}", usage)),
+            _ => Ok(rest.to_string()),
+        };
+        match word {
+            "" => Ok(ConsoleCommand::Empty),
+            "sids" => Ok(ConsoleCommand::Sids),
+            "history" => Ok(ConsoleCommand::History),
+            "!!" => Ok(ConsoleCommand::Repeat(None)),
+            "save" => Ok(ConsoleCommand::Save(argument("save <file>")?)),
+            "run" => Ok(ConsoleCommand::Run(argument("run <file>")?)),
+            "help" | "?" => Ok(ConsoleCommand::Help),
+            "quit" | "exit" => Ok(ConsoleCommand::Quit),
+            _ if word.starts_with('!') => match word[1..].parse() {
+                Ok(n) if n > 0 => Ok(ConsoleCommand::Repeat(Some(n))),
+                _ => Err(format!("no history entry {}", &word[1..])),
+            },
+            _ => {
+                let sid = parse_sid(word)?;
+                let payload: String = rest.split_whitespace().collect();
+                let payload = packet::parse_hex(&payload).ok_or_else(|| format!("invalid hex payload {}", rest))?;
+                Ok(ConsoleCommand::Send { sid, payload })
+            }
+        }
+    }
+}
+
+// Codes SID does not define are sent as they are, to probe the protocol
+fn parse_sid(word: &str) -> Result<u16, String> {
+    if let Some(sid) = SID::from_name(word) {
+        return Ok(sid as u16);
+    }
+    u16::from_str_radix(word.trim_start_matches("0x"), 16).map_err(|_| format!("unknown command or SID {}", word))
+}
+
+// Console spelling of a SID code, its name when SID has one
+fn sid_name(code: u16) -> String {
+    match SID::from_u16(code) {
+        Some(sid) if sid != SID::UNKNOWN => format!("{:?}", sid),
+        _ => format!("0x{:04x}", code),
+    }
+}
+
+pub struct Console<'a> {
+    camera: &'a mut Camera,
+    annotator: Annotator,
+    // Sent packets as console lines, e.g. "SUPPORT_FUNCTION_INFO 04"
+    history: Vec<String>,
+    // Scripts being run, a script running itself would never end
+    running: Vec<PathBuf>,
+    start: Instant,
+    // Kept below the keepalive interval, so pings cannot take the reply
+    pub reply_timeout: Duration,
+}
+
+impl<'a> Console<'a> {
+    pub fn new(camera: &'a mut Camera) -> Self {
+        let annotator = match camera.capabilities() {
+            Some(capabilities) => Annotator::with_versions(capabilities.versions.clone()),
+            None => Annotator::new(),
+        };
+        Console {
+            camera,
+            annotator,
+            history: Vec::new(),
+            running: Vec::new(),
+            start: Instant::now(),
+            reply_timeout: Duration::from_secs(3),
+        }
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    // Run one line, printing frames and errors to out
+    // Returns false once the user quits
+    pub async fn execute(&mut self, line: &str, out: &mut dyn Write) -> io::Result<bool> {
+        match line.parse() {
+            Ok(command) => self.run_command(command, out).await,
+            Err(err) => {
+                writeln!(out, "error: {}", err)?;
+                Ok(true)
+            }
+        }
+    }
+
+    async fn run_command(&mut self, command: ConsoleCommand, out: &mut dyn Write) -> io::Result<bool> {
+        match command {
+            ConsoleCommand::Send { sid, payload } => {
+                self.history.push(format!("{} {}", sid_name(sid), packet::hex(&payload)).trim_end().to_string());
+                self.send(sid, &payload, out).await?;
+            }
+            ConsoleCommand::Repeat(entry) => {
+                let line = match entry {
+                    Some(n) => self.history.get(n - 1),
+                    None => self.history.last(),
+                };
+                match line.cloned() {
+                    Some(line) => {
+                        writeln!(out, "> {}", line)?;
+                        return Box::pin(self.execute(&line, out)).await;
+                    }
+                    None => writeln!(out, "error: no such history entry")?,
+                }
+            }
+            ConsoleCommand::Run(file) => return self.run_script(&file, out).await,
+            ConsoleCommand::Save(file) => {
+                let mut script = String::from("# instax_pal console script\n");
+                script.extend(self.history.iter().map(|line| format!("{}\n", line)));
+                match fs::write(&file, script) {
+                    Ok(()) => writeln!(out, "saved {} packets to {}", self.history.len(), file)?,
+                    Err(err) => writeln!(out, "error: cannot write {}: {}", file, err)?,
+                }
+            }
+            ConsoleCommand::History => {
+                for (i, line) in self.history.iter().enumerate() {
+                    writeln!(out, "{:>4}  {}", i + 1, line)?;
+                }
+            }
+            ConsoleCommand::Sids => {
+                for sid in SID::all() {
+                    writeln!(out, "0x{:04x}  {:?}", sid as u16, sid)?;
+                }
+            }
+            ConsoleCommand::Help => writeln!(out, "{}", HELP)?,
+            ConsoleCommand::Quit => return Ok(false),
+            ConsoleCommand::Empty => {}
+        }
+        Ok(true)
+    }
+
+    async fn run_script(&mut self, file: &str, out: &mut dyn Write) -> io::Result<bool> {
+        let script = fs::canonicalize(file).and_then(|path| fs::read_to_string(&path).map(|script| (path, script)));
+        let (path, script) = match script {
+            Ok(script) => script,
+            Err(err) => {
+                writeln!(out, "error: cannot read {}: {}", file, err)?;
+                return Ok(true);
+            }
+        };
+        if self.running.contains(&path) {
+            writeln!(out, "error: {} is already running", file)?;
+            return Ok(true);
+        }
+        self.running.push(path);
+        let mut result = Ok(true);
+        for line in script.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            result = match writeln!(out, "> {}", line) {
+                Ok(()) => Box::pin(self.execute(line, out)).await,
+                Err(err) => Err(err),
+            };
+            if !matches!(result, Ok(true)) {
+                break;
+            }
+        }
+        self.running.pop();
+        result
+    }
+
+    // Send the packet and print every frame until the reply with the same SID
+    // Goes straight to the link, so SIDs the camera does not announce, or
+    // that SID does not define, can be probed too.
+    async fn send(&mut self, sid: u16, payload: &[u8], out: &mut dyn Write) -> io::Result<()> {
+        let data = packet::request_frame(sid, payload);
+        self.print(FrameDirection::Sent, &data, out)?;
+        if let Err(err) = self.camera.send_data(data).await {
+            return writeln!(out, "error: {}", err);
+        }
+        let deadline = Instant::now() + self.reply_timeout;
+        loop {
+            let data = match timeout_at(deadline, self.camera.receive_data()).await {
+                Ok(Some(data)) => data,
+                Ok(None) => return writeln!(out, "error: {}", CameraError::Disconnected),
+                Err(_) => return writeln!(out, "error: no response to {}", sid_name(sid)),
+            };
+            self.print(FrameDirection::Received, &data, out)?;
+            if packet::read_u16(&data, 4) == Some(sid) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn print(&mut self, direction: FrameDirection, data: &[u8], out: &mut dyn Write) -> io::Result<()> {
+        let frame = Frame { time: self.start.elapsed(), direction, data: data.to_vec() };
+        writeln!(out, "{}", self.annotator.entry(&frame))
+    }
+}
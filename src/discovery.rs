@@ -0,0 +1,282 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use bluer::agent::{Agent, AgentHandle};
+use bluer::{Adapter, AdapterEvent, Address, Device, DiscoveryFilter, DiscoveryTransport, Session};
+use futures::{pin_mut, Stream, StreamExt};
+use tokio::time::{sleep_until, Instant};
+use tracing::{info, warn};
+
+use crate::advertisement::Advertisement;
+use crate::camera::{CameraError, INSTAX_SERVICE_UUID};
+use crate::recording::SessionRecorder;
+
+// Which camera to connect to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum DeviceSelector {
+    // The camera with the strongest signal
+    #[default]
+    Any,
+    Address(Address),
+    // Bluetooth alias, usually the advertised name unless renamed
+    Alias(String),
+}
+
+impl DeviceSelector {
+    pub fn matches(&self, camera: &DiscoveredCamera) -> bool {
+        match self {
+            DeviceSelector::Any => true,
+            DeviceSelector::Address(address) => camera.address == *address,
+            DeviceSelector::Alias(alias) => camera.alias.eq_ignore_ascii_case(alias),
+        }
+    }
+}
+
+// Addresses take precedence, anything else is taken as an alias
+impl FromStr for DeviceSelector {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.parse::<Address>() {
+            Ok(address) => DeviceSelector::Address(address),
+            Err(_) => DeviceSelector::Alias(s.to_string()),
+        })
+    }
+}
+
+// How Camera::connect finds and connects to a camera
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    // Adapter name such as hci1, the default adapter when None
+    pub adapter: Option<String>,
+    pub device: DeviceSelector,
+    pub scan_timeout: Duration,
+    // Pair and trust the camera before connecting
+    pub pair: bool,
+    // Record every frame of the session
+    pub record: Option<SessionRecorder>,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        ConnectOptions {
+            adapter: None,
+            device: DeviceSelector::Any,
+            scan_timeout: Duration::from_secs(5),
+            pair: false,
+            record: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiscoveredCamera {
+    pub address: Address,
+    pub name: Option<String>,
+    pub alias: String,
+    // None for known devices that were not heard during the scan
+    pub rssi: Option<i16>,
+    pub is_paired: bool,
+    pub advertisement: Advertisement,
+}
+
+impl DiscoveredCamera {
+    async fn from_device(device: &Device) -> Result<Self, CameraError> {
+        Ok(DiscoveredCamera {
+            address: device.address(),
+            name: device.name().await?,
+            alias: device.alias().await?,
+            rssi: device.rssi().await?,
+            is_paired: device.is_paired().await?,
+            advertisement: Advertisement::parse(
+                &device.manufacturer_data().await?.unwrap_or_default(),
+                &device.service_data().await?.unwrap_or_default(),
+            ),
+        })
+    }
+}
+
+impl fmt::Display for DiscoveredCamera {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}  {:<20}", self.address, self.alias)?;
+        match self.rssi {
+            Some(rssi) => write!(f, "  {:>4} dBm", rssi)?,
+            None => write!(f, "      n/a")?,
+        }
+        if self.is_paired {
+            write!(f, "  paired")?;
+        }
+        let advertisement = &self.advertisement;
+        if let Some(is_awake) = advertisement.is_awake {
+            write!(f, "  {}", if is_awake { "awake" } else { "asleep" })?;
+        }
+        if let Some(battery_level) = advertisement.battery_level {
+            write!(f, "  battery {}{}", battery_level, if advertisement.is_charging == Some(true) { "+" } else { "" })?;
+        }
+        if let Some(model_id) = advertisement.model_id {
+            write!(f, "  model {:#04x}", model_id)?;
+        }
+        Ok(())
+    }
+}
+
+pub async fn adapter_names() -> Result<Vec<String>, CameraError> {
+    let session = Session::new().await?;
+    Ok(session.adapter_names().await?)
+}
+
+pub async fn open_adapter(session: &Session, name: Option<&str>) -> Result<Adapter, CameraError> {
+    let adapter = match name {
+        Some(name) => session.adapter(name)?,
+        None => session.default_adapter().await?,
+    };
+    adapter.set_powered(true).await?;
+    Ok(adapter)
+}
+
+async fn has_instax_service(device: &Device) -> Result<bool, CameraError> {
+    Ok(device.uuids().await?.is_some_and(|uuids| uuids.contains(&INSTAX_SERVICE_UUID)))
+}
+
+async fn start_discovery(adapter: &Adapter) -> Result<impl Stream<Item = AdapterEvent>, CameraError> {
+    adapter.set_discovery_filter(DiscoveryFilter {
+        uuids: HashSet::from([INSTAX_SERVICE_UUID]),
+        transport: DiscoveryTransport::Le,
+        // Report every advertisement so RSSI and advertised state stay current
+        duplicate_data: true,
+        ..Default::default()
+    }).await?;
+    Ok(adapter.discover_devices().await?)
+}
+
+// Devices that vanish or fail to answer while being listed are skipped
+async fn instax_cameras(adapter: &Adapter, addresses: &HashSet<Address>) -> Vec<DiscoveredCamera> {
+    let mut cameras = Vec::new();
+    for &address in addresses {
+        let camera = async {
+            let device = adapter.device(address)?;
+            match has_instax_service(&device).await? {
+                true => DiscoveredCamera::from_device(&device).await.map(Some),
+                false => Ok(None),
+            }
+        };
+        match camera.await {
+            Ok(Some(camera)) => cameras.push(camera),
+            Ok(None) => {}
+            Err(err) => warn!(%address, %err, "skipping device"),
+        }
+    }
+    cameras.sort_by_key(|camera| std::cmp::Reverse(camera.rssi.unwrap_or(i16::MIN)));
+    cameras
+}
+
+// Scan for INSTAX_SERVICE_UUID advertisements
+// Known cameras that do not advertise, e.g. because they are connected, are
+// listed as well. Sorted by signal strength.
+pub async fn scan(adapter: &Adapter, timeout: Duration) -> Result<Vec<DiscoveredCamera>, CameraError> {
+    let mut addresses: HashSet<Address> = adapter.device_addresses().await?.into_iter().collect();
+    let events = start_discovery(adapter).await?;
+    pin_mut!(events);
+    let deadline = Instant::now() + timeout;
+    loop {
+        tokio::select! {
+            event = events.next() => match event {
+                Some(AdapterEvent::DeviceAdded(address)) => { addresses.insert(address); }
+                Some(_) => {}
+                None => break,
+            },
+            _ = sleep_until(deadline) => break,
+        }
+    }
+    Ok(instax_cameras(adapter, &addresses).await)
+}
+
+// Scan until the callback returns false, passing it the cameras heard so far
+// every refresh interval. Nothing is connected, so sleeping cameras stay asleep.
+pub async fn watch<F>(adapter: &Adapter, refresh: Duration, mut on_update: F) -> Result<(), CameraError>
+where
+    F: FnMut(&[DiscoveredCamera]) -> bool,
+{
+    let mut addresses = HashSet::new();
+    let events = start_discovery(adapter).await?;
+    pin_mut!(events);
+    let mut next_refresh = Instant::now() + refresh;
+    loop {
+        tokio::select! {
+            event = events.next() => match event {
+                Some(AdapterEvent::DeviceAdded(address)) => { addresses.insert(address); }
+                Some(AdapterEvent::DeviceRemoved(address)) => { addresses.remove(&address); }
+                Some(_) => {}
+                None => return Ok(()),
+            },
+            _ = sleep_until(next_refresh) => {
+                if !on_update(&instax_cameras(adapter, &addresses).await) {
+                    return Ok(());
+                }
+                next_refresh = Instant::now() + refresh;
+            }
+        }
+    }
+}
+
+// Scan and pick the camera matching the selector
+// Any takes the strongest signal, an alias matching several cameras fails
+pub async fn find_camera(adapter: &Adapter, selector: &DeviceSelector, timeout: Duration) -> Result<Device, CameraError> {
+    let cameras: Vec<DiscoveredCamera> = scan(adapter, timeout).await?
+        .into_iter()
+        .filter(|camera| selector.matches(camera))
+        .collect();
+    match cameras.as_slice() {
+        [] => Err(CameraError::NotFound(match selector {
+            DeviceSelector::Any => "no Instax camera found".to_string(),
+            DeviceSelector::Address(address) => format!("no Instax camera with address {}", address),
+            DeviceSelector::Alias(alias) => format!("no Instax camera named {}", alias),
+        })),
+        [camera] => Ok(adapter.device(camera.address)?),
+        [camera, ..] if *selector == DeviceSelector::Any => {
+            info!(address = %camera.address, alias = %camera.alias, found = cameras.len(), "picking the strongest camera");
+            Ok(adapter.device(camera.address)?)
+        }
+        cameras => {
+            let found: Vec<String> = cameras.iter().map(|camera| format!("{} ({})", camera.address, camera.alias)).collect();
+            Err(CameraError::NotFound(format!("several cameras found, select one of {}", found.join(", "))))
+        }
+    }
+}
+
+// Agent accepting pairing requests, kept registered while the handle lives
+// The camera has no display or keyboard, so just-works pairing is expected
+pub async fn register_agent(session: &Session) -> Result<AgentHandle, CameraError> {
+    let agent = Agent {
+        request_default: true,
+        request_confirmation: Some(Box::new(|request| {
+            Box::pin(async move {
+                info!(device = %request.device, passkey = format!("{:06}", request.passkey), "confirming pairing");
+                Ok(())
+            })
+        })),
+        request_authorization: Some(Box::new(|request| {
+            Box::pin(async move {
+                info!(device = %request.device, "authorizing");
+                Ok(())
+            })
+        })),
+        ..Default::default()
+    };
+    Ok(session.register_agent(agent).await?)
+}
+
+// Pair and trust the device unless already done
+pub async fn pair(session: &Session, device: &Device) -> Result<(), CameraError> {
+    let _agent = register_agent(session).await?;
+    if !device.is_paired().await? {
+        info!(address = %device.address(), "pairing");
+        device.pair().await?;
+    }
+    if !device.is_trusted().await? {
+        device.set_trusted(true).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep_until, timeout, Instant};
+use tracing::{debug, warn};
+
+use crate::camera::{Link, Shared};
+use crate::connection::ConnectionState;
+use crate::packet::{self, Packet};
+use crate::{SupportFunctionInfoType, SID};
+
+#[derive(Debug, Clone)]
+pub struct KeepaliveConfig {
+    // Idle time after which the camera is pinged
+    pub interval: Duration,
+    // Retry interval after a missed reply
+    pub retry_interval: Duration,
+    pub reply_timeout: Duration,
+    // Consecutive missed replies before the connection is considered lost
+    pub max_missed: u32,
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        KeepaliveConfig {
+            interval: Duration::from_secs(4),
+            retry_interval: Duration::from_secs(1),
+            reply_timeout: Duration::from_secs(2),
+            max_missed: 3,
+        }
+    }
+}
+
+// Background task pinging the camera with CAMERA_FUNCTION_INFO while idle
+// Any other traffic postpones the next ping, and pings wait for in-flight
+// requests to release the link. Aborted when dropped.
+pub struct Keepalive {
+    handle: JoinHandle<()>,
+    lost: watch::Receiver<bool>,
+}
+
+impl Keepalive {
+    pub(crate) fn spawn(shared: Arc<Shared>, config: KeepaliveConfig) -> Self {
+        let (lost_sender, lost) = watch::channel(false);
+        let handle = tokio::spawn(async move {
+            run(shared.clone(), config).await;
+            shared.set_state(ConnectionState::Disconnected);
+            let _ = lost_sender.send(true);
+        });
+        Keepalive { handle, lost }
+    }
+
+    pub fn is_lost(&self) -> bool {
+        *self.lost.borrow()
+    }
+
+    // Resolves once keepalive replies stopped arriving
+    pub async fn lost(&self) {
+        let mut lost = self.lost.clone();
+        let _ = lost.wait_for(|lost| *lost).await;
+    }
+}
+
+impl Drop for Keepalive {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+// Returns when the connection is lost
+async fn run(shared: Arc<Shared>, config: KeepaliveConfig) {
+    let mut interval = config.interval;
+    let mut missed = 0;
+    loop {
+        let due = shared.last_activity() + interval;
+        if Instant::now() < due {
+            sleep_until(due).await;
+            continue;
+        }
+        let mut link = shared.link.lock().await;
+        // A request went through while waiting for the link
+        if shared.last_activity() + interval > Instant::now() {
+            continue;
+        }
+        let ping = Packet::with_type(SID::SUPPORT_FUNCTION_INFO, SupportFunctionInfoType::CAMERA_FUNCTION_INFO as u8).pack();
+        link.drain();
+        let replied = link.send_data(ping.clone()).await.is_ok()
+            && timeout(config.reply_timeout, receive_reply(&mut link, &ping)).await.unwrap_or(false);
+        drop(link);
+        shared.touch();
+        if replied {
+            missed = 0;
+            interval = config.interval;
+        } else {
+            missed += 1;
+            interval = config.retry_interval;
+            warn!(missed, max_missed = config.max_missed, "keepalive reply missed");
+            if missed >= config.max_missed {
+                warn!("connection to camera lost");
+                return;
+            }
+        }
+    }
+}
+
+// Waits for the reply to the ping, skipping frames that answer something else
+// False once the link is gone
+async fn receive_reply(link: &mut Link, ping: &[u8]) -> bool {
+    while let Some(data) = link.receive_data().await {
+        if packet::answers(ping, &data) {
+            return true;
+        }
+        debug!(frame = %packet::describe(&data), "skipping frame while waiting for keepalive reply");
+    }
+    false
+}
@@ -1,20 +1,61 @@
+use std::error::Error;
+use std::fmt;
 use num_traits::FromPrimitive;
-use num_derive::FromPrimitive;
+use num_derive::{FromPrimitive, ToPrimitive};
+
+use crate::camera::CameraError;
+use crate::codec::{layout, Decode, Encode};
+use crate::codec::{Bits, Bool, Bytes, Enum, EnumOr, ErrorCode, Flag, Optional, Status, Text, U16Be, U16Le, U32Be, U32Le, U8};
+
+pub mod advertisement;
+pub mod avi;
+pub mod battery;
+pub mod btsnoop;
+pub mod camera;
+pub mod capabilities;
+pub mod codec;
+pub mod connection;
+pub mod console;
+pub mod discovery;
+pub mod keepalive;
+pub mod manager;
+pub mod packet;
+pub mod profile;
+pub mod recording;
+pub mod timeline;
+pub mod transport;
+pub mod upload;
 
 // Instax protocol direction: to or from device
 #[derive(Debug, FromPrimitive, Copy, Clone)]
 #[allow(non_camel_case_types)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Direction {
     TO = 0x4162,    // "Ab"
     FROM = 0x6142, // "aB"
 }
 
-// SID: Instax protocol opcodes
-// u16 with modeCode, typeCode as big endian
-#[derive(Debug, FromPrimitive, Copy, Clone)]
-#[allow(non_camel_case_types)]
-pub enum SID {
-    UNKNOWN = 0xffff,
+// Declares SID together with SID::ALL, the list of its named variants
+macro_rules! sids {
+    ($($name:ident = $code:expr,)*) => {
+        // SID: Instax protocol opcodes
+        // u16 with modeCode, typeCode as big endian
+        #[derive(Debug, FromPrimitive, Copy, Clone, PartialEq)]
+        #[allow(non_camel_case_types)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub enum SID {
+            UNKNOWN = 0xffff,
+            $($name = $code,)*
+        }
+
+        impl SID {
+            // Every defined SID with its name in code order, without UNKNOWN
+            pub const ALL: &'static [(SID, &'static str)] = &[$((SID::$name, stringify!($name)),)*];
+        }
+    };
+}
+
+sids! {
     SUPPORT_FUNCTION_AND_VERSION_INFO = 0x0000,
     DEVICE_INFO_SERVICE = 0x0001,
     SUPPORT_FUNCTION_INFO = 0x0002,
@@ -110,9 +151,111 @@ pub enum SID {
     INTERVAL_RECORD_STOP = 0x8902,
 }
 
+impl SID {
+    // Every defined SID in code order, without UNKNOWN
+    pub fn all() -> impl Iterator<Item = SID> {
+        Self::ALL.iter().map(|(sid, _)| *sid)
+    }
+
+    // Case-insensitive lookup, e.g. "check_camera_status"
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|(_, sid_name)| sid_name.eq_ignore_ascii_case(name)).map(|(sid, _)| *sid)
+    }
+
+    // Commands that can be sent again after a lost reply without side effects:
+    // queries, chunk reads by index and settings written as absolute values.
+    // Commands, transfers and power control are not, a second SHUTTER takes a
+    // second picture.
+    pub fn is_idempotent(&self) -> bool {
+        matches!(
+            self,
+            SID::SUPPORT_FUNCTION_AND_VERSION_INFO
+                | SID::DEVICE_INFO_SERVICE
+                | SID::SUPPORT_FUNCTION_INFO
+                | SID::IDENTIFY_INFORMATION
+                | SID::AUTO_SLEEP_SETTINGS
+                | SID::CAMERA_SETTINGS
+                | SID::CAMERA_SETTINGS_GET
+                | SID::PARAMETER_RW
+                | SID::TIME_SETTING
+                | SID::URL_UPLOAD_INFO
+                | SID::URL_PICTURE_UPLOAD
+                | SID::URL_AUDIO_UPLOAD
+                | SID::CAMERA_LOG_SUBTOTAL_DATA
+                | SID::CAMERA_LOG_DATE_DATA
+                | SID::CAMERA_LOG_FILTER_DATA
+                | SID::CAMERA_LOG_RECORD_DATE_DATA
+                | SID::CHECK_CAMERA_STATUS
+                | SID::IMAGE_MANUAL_UPLOAD_INFO
+                | SID::IMAGE_AUTO_UPLOAD_INFO
+                | SID::IMAGE_AUTO_UPLOAD_DATA
+                | SID::INTERVAL_RECORD_STATUS
+        )
+    }
+}
+
+// Result code in the first payload byte of replies, see StatusReply
+// 0x81 is what IMAGE_AUTO_UPLOAD_INFO answers with no photo queued.
+// Unverified: 0x82 to 0x84 are guessed to follow it in the same block, no
+// capture shows them yet.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResponseStatus {
+    OK,
+    NO_DATA,
+    BUSY,
+    INVALID_PARAMETER,
+    NOT_SUPPORTED,
+    // Any other non-zero code
+    ERROR(u8),
+}
+
+impl ResponseStatus {
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            0x00 => ResponseStatus::OK,
+            0x81 => ResponseStatus::NO_DATA,
+            0x82 => ResponseStatus::BUSY,
+            0x83 => ResponseStatus::INVALID_PARAMETER,
+            0x84 => ResponseStatus::NOT_SUPPORTED,
+            code => ResponseStatus::ERROR(code),
+        }
+    }
+
+    pub fn code(&self) -> u8 {
+        match self {
+            ResponseStatus::OK => 0x00,
+            ResponseStatus::NO_DATA => 0x81,
+            ResponseStatus::BUSY => 0x82,
+            ResponseStatus::INVALID_PARAMETER => 0x83,
+            ResponseStatus::NOT_SUPPORTED => 0x84,
+            ResponseStatus::ERROR(code) => *code,
+        }
+    }
+
+    // Whether a capture shows the camera sending this status
+    pub fn is_verified(&self) -> bool {
+        matches!(self, ResponseStatus::OK | ResponseStatus::NO_DATA)
+    }
+}
+
+// Reply that only acknowledges a request, e.g. EXECUTE_CAMERA_COMMAND
+// Layout: status(1)
+// Replies with data after the status declare it as their first field, so the
+// status of any of them decodes as a StatusReply.
+layout! {
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct StatusReply {
+        pub status: ResponseStatus = Status(0),
+    }
+}
+
 // Payload for SUPPORT_FUNCTION_INFO
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone)]
+#[derive(Debug, FromPrimitive, ToPrimitive, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SupportFunctionInfoType {
     IMAGE_SUPPORT_INFO = 0,
     BATTERY_INFO = 1,
@@ -124,6 +267,8 @@ pub enum SupportFunctionInfoType {
 
 // Payload for DEVICE_INFO
 #[allow(non_camel_case_types)]
+#[derive(Debug, FromPrimitive, ToPrimitive, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DeviceInfoType {
     MANUFACTURER_NAME = 0,
     MODEL_NUMBER = 1,
@@ -136,8 +281,23 @@ pub enum DeviceInfoType {
     PNP_ID = 8,
 }
 
+impl DeviceInfoType {
+    pub const ALL: [DeviceInfoType; 9] = [
+        DeviceInfoType::MANUFACTURER_NAME,
+        DeviceInfoType::MODEL_NUMBER,
+        DeviceInfoType::SERIAL_NUMBER,
+        DeviceInfoType::HW_REVISION,
+        DeviceInfoType::FW_REVISION,
+        DeviceInfoType::SW_REVISION,
+        DeviceInfoType::SYSTEM_ID,
+        DeviceInfoType::REGULATORY_DATA,
+        DeviceInfoType::PNP_ID,
+    ];
+}
+
 #[allow(non_camel_case_types)]
-#[derive(Debug, FromPrimitive)]
+#[derive(Debug, FromPrimitive, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CameraErrorType {
     NO_ERROR = -1,
     BATTERY_NG_ERROR = 0,
@@ -152,7 +312,65 @@ pub enum CameraErrorType {
     RESERVED_ERROR = -2,
 }
 
+impl CameraErrorType {
+    // Error codes are signed, unknown codes map to RESERVED_ERROR
+    pub fn from_code(code: i16) -> Self {
+        FromPrimitive::from_i16(code).unwrap_or(CameraErrorType::RESERVED_ERROR)
+    }
+}
+
+// Payload for EXECUTE_CAMERA_COMMAND
+// Unverified: no capture of this SID exists yet, the codes only follow the
+// order of the camera's menu actions and may be wrong
+#[allow(non_camel_case_types)]
+#[derive(Debug, FromPrimitive, ToPrimitive, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CameraCommand {
+    SHUTTER = 0,
+    PRINT = 1,
+    CANCEL = 2,
+}
+
+// Usage logs kept by the camera, each read with a START / DATA SID pair
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CameraLogType {
+    SUBTOTAL,
+    DATE,
+    FILTER,
+    RECORD_DATE,
+}
+
+impl CameraLogType {
+    pub const ALL: [CameraLogType; 4] = [
+        CameraLogType::SUBTOTAL,
+        CameraLogType::DATE,
+        CameraLogType::FILTER,
+        CameraLogType::RECORD_DATE,
+    ];
+
+    pub fn start_sid(&self) -> SID {
+        match self {
+            CameraLogType::SUBTOTAL => SID::CAMERA_LOG_SUBTOTAL_START,
+            CameraLogType::DATE => SID::CAMERA_LOG_DATE_START,
+            CameraLogType::FILTER => SID::CAMERA_LOG_FILTER_START,
+            CameraLogType::RECORD_DATE => SID::CAMERA_LOG_RECORD_DATE_START,
+        }
+    }
+
+    pub fn data_sid(&self) -> SID {
+        match self {
+            CameraLogType::SUBTOTAL => SID::CAMERA_LOG_SUBTOTAL_DATA,
+            CameraLogType::DATE => SID::CAMERA_LOG_DATE_DATA,
+            CameraLogType::FILTER => SID::CAMERA_LOG_FILTER_DATA,
+            CameraLogType::RECORD_DATE => SID::CAMERA_LOG_RECORD_DATE_DATA,
+        }
+    }
+}
+
 #[allow(non_camel_case_types)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ActiveMedia {
     SD = 0,
     BUILT_IN_MEDIA = 1,
@@ -160,7 +378,8 @@ pub enum ActiveMedia {
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Debug, FromPrimitive)]
+#[derive(Debug, FromPrimitive, ToPrimitive, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ReadWriteSettingType {
     AUTO_POWER_OFF = 0,
     LED_AT_START_UP = 1,
@@ -181,158 +400,648 @@ pub enum ReadWriteSettingType {
     DELETE_ORIGINAL_IMAGE_AFTER_TRANSFER = 18,
 }
 
+impl ReadWriteSettingType {
+    pub const ALL: [ReadWriteSettingType; 17] = [
+        ReadWriteSettingType::AUTO_POWER_OFF,
+        ReadWriteSettingType::LED_AT_START_UP,
+        ReadWriteSettingType::AUTOMATIC_PICTURE_DELETION,
+        ReadWriteSettingType::VOLUME_SETTING,
+        ReadWriteSettingType::POWER_ON_SOUND_TYPE,
+        ReadWriteSettingType::VOICE_SHUTTER_SOUND_TYPE,
+        ReadWriteSettingType::BGM_SOUND_TYPE,
+        ReadWriteSettingType::DATE_PRINT_SETTING,
+        ReadWriteSettingType::DATE_PRINT_ORDER_SETTING,
+        ReadWriteSettingType::THREED_LUT_PRINT_SETTING,
+        ReadWriteSettingType::TRANSFER_FORMAT,
+        ReadWriteSettingType::FLASH_SETTING,
+        ReadWriteSettingType::EXPOSURE_SETTING,
+        ReadWriteSettingType::LONG_INTERVAL_SHOOT_TIME,
+        ReadWriteSettingType::SHORT_INTERVAL_SHOOT_TIME,
+        ReadWriteSettingType::VOICE_LANGUAGE_SETTINGS,
+        ReadWriteSettingType::DELETE_ORIGINAL_IMAGE_AFTER_TRANSFER,
+    ];
+
+    // Setting name as used in profiles, e.g. "FLASH_SETTING"
+    pub fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|setting| setting.name() == name)
+    }
+}
+
 #[allow(non_camel_case_types)]
-#[derive(Debug, FromPrimitive)]
+#[derive(Debug, FromPrimitive, ToPrimitive, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ReadWriteSettingMode {
     GET_CURRENT_SETTING = 0,
     GET_FACTORY_SETTING = 1,
     SET_FACTORY_SETTING = 2,
 }
 
+// Payload for INTERVAL_RECORD_START
+// Selects which of LONG_/SHORT_INTERVAL_SHOOT_TIME is used between shots
+#[allow(non_camel_case_types)]
+#[derive(Debug, FromPrimitive, ToPrimitive, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IntervalRecordMode {
+    SHORT_INTERVAL = 0,
+    LONG_INTERVAL = 1,
+}
+
+impl IntervalRecordMode {
+    pub fn shoot_time_setting(&self) -> ReadWriteSettingType {
+        match self {
+            IntervalRecordMode::SHORT_INTERVAL => ReadWriteSettingType::SHORT_INTERVAL_SHOOT_TIME,
+            IntervalRecordMode::LONG_INTERVAL => ReadWriteSettingType::LONG_INTERVAL_SHOOT_TIME,
+        }
+    }
+}
+
 #[derive(Debug)]
-pub struct SupportFunctionVersionInfo {
-    pub support_function_info: u8,
-    pub device_info_version: u8,
-    pub support_img_info_version: u8,
-    pub battery_info_version: u8,
-    pub printer_func_info_version: u8,
-    pub printer_history_info_version: u8,
-    pub camera_func_info_version: u8,
-    pub camera_history_info_version: u8,
+pub enum DecodeError {
+    TooShort { expected: usize, actual: usize },
+    UnknownInfoType(u8),
+    UnsupportedVersion { info_type: SupportFunctionInfoType, version: u8 },
+    // Enum byte the crate has no value for, field names what it encodes
+    InvalidValue { field: &'static str, at: usize, value: u8 },
+    // Frame header words the crate has no value for
+    UnknownDirection(u16),
+    UnknownSid(u16),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::TooShort { expected, actual } => write!(f, "payload too short: expected {} bytes, got {}", expected, actual),
+            DecodeError::UnknownInfoType(info_type) => write!(f, "unknown info type {}", info_type),
+            DecodeError::UnsupportedVersion { info_type, version } => write!(f, "unsupported {:?} version {}", info_type, version),
+            DecodeError::InvalidValue { field, value, .. } => write!(f, "unknown {} {}", field, value),
+            DecodeError::UnknownDirection(direction) => write!(f, "unknown direction 0x{:04x}", direction),
+            DecodeError::UnknownSid(sid) => write!(f, "unknown SID 0x{:04x}", sid),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+fn check_len(bytes: &[u8], expected: usize) -> Result<(), DecodeError> {
+    if bytes.len() < expected {
+        return Err(DecodeError::TooShort { expected, actual: bytes.len() });
+    }
+    Ok(())
+}
+
+layout! {
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct SupportFunctionVersionInfo {
+        pub support_function_info: u8 = U8(0),
+        pub device_info_version: u8 = U8(1),
+        pub support_img_info_version: u8 = U8(2),
+        pub battery_info_version: u8 = U8(3),
+        pub printer_func_info_version: u8 = U8(4),
+        pub printer_history_info_version: u8 = U8(5),
+        pub camera_func_info_version: u8 = U8(6),
+        pub camera_history_info_version: u8 = U8(7),
+    }
 }
 
 impl SupportFunctionVersionInfo {
-    pub fn from_bytes(bytes: &Vec<u8>) -> Self {
-        SupportFunctionVersionInfo {
-            support_function_info: bytes[0],
-            device_info_version: bytes[1],
-            support_img_info_version: bytes[2],
-            battery_info_version: bytes[3],
-            printer_func_info_version: bytes[4],
-            printer_history_info_version: bytes[5],
-            camera_func_info_version: bytes[6],
-            camera_history_info_version: bytes[7],
+    // Layout version the device uses for an info type, 0 if unsupported
+    pub fn version_of(&self, info_type: SupportFunctionInfoType) -> u8 {
+        match info_type {
+            SupportFunctionInfoType::IMAGE_SUPPORT_INFO => self.support_img_info_version,
+            SupportFunctionInfoType::BATTERY_INFO => self.battery_info_version,
+            SupportFunctionInfoType::PRINTER_FUNCTION_INFO => self.printer_func_info_version,
+            SupportFunctionInfoType::PRINT_HISTORY_INFO => self.printer_history_info_version,
+            SupportFunctionInfoType::CAMERA_FUNCTION_INFO => self.camera_func_info_version,
+            SupportFunctionInfoType::CAMERA_HISTORY_INFO => self.camera_history_info_version,
         }
     }
 }
 
 // SUPPORT_FUNCTION_INFO
+// Layout: status(1) + SupportFunctionInfoType(1) + info
+// Each info decoder takes the layout version from SupportFunctionVersionInfo
 
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SupportFunctionInfoResponse {
     ImageSupportInfo(ImageSupportInfo),
     BatteryInfo(BatteryInfo),
+    PrinterFunctionInfo(PrinterFunctionInfo),
+    PrintHistoryInfo(PrintHistoryInfo),
     CameraFunctionInfo(CameraFunctionInfo),
     CameraHistoryInfo(CameraHistoryInfo),
 }
 
-#[derive(Debug)]
-pub struct ImageSupportInfo {
-    pub width: u16,
-    pub height: u16,
-    pub pic_type: u8,
-    pub pic_option: u8,
-    pub size: u32,
+impl SupportFunctionInfoResponse {
+    pub fn decode(bytes: &[u8], versions: &SupportFunctionVersionInfo) -> Result<Self, DecodeError> {
+        check_len(bytes, 2)?;
+        let info_type: SupportFunctionInfoType = FromPrimitive::from_u8(bytes[1])
+            .ok_or(DecodeError::UnknownInfoType(bytes[1]))?;
+        let version = versions.version_of(info_type);
+        let response = match info_type {
+            SupportFunctionInfoType::IMAGE_SUPPORT_INFO => {
+                SupportFunctionInfoResponse::ImageSupportInfo(ImageSupportInfo::decode(bytes, version)?)
+            }
+            SupportFunctionInfoType::BATTERY_INFO => {
+                SupportFunctionInfoResponse::BatteryInfo(BatteryInfo::decode(bytes, version)?)
+            }
+            SupportFunctionInfoType::PRINTER_FUNCTION_INFO => {
+                SupportFunctionInfoResponse::PrinterFunctionInfo(PrinterFunctionInfo::decode(bytes, version)?)
+            }
+            SupportFunctionInfoType::PRINT_HISTORY_INFO => {
+                SupportFunctionInfoResponse::PrintHistoryInfo(PrintHistoryInfo::decode(bytes, version)?)
+            }
+            SupportFunctionInfoType::CAMERA_FUNCTION_INFO => {
+                SupportFunctionInfoResponse::CameraFunctionInfo(CameraFunctionInfo::decode(bytes, version)?)
+            }
+            SupportFunctionInfoType::CAMERA_HISTORY_INFO => {
+                SupportFunctionInfoResponse::CameraHistoryInfo(CameraHistoryInfo::decode(bytes, version)?)
+            }
+        };
+        Ok(response)
+    }
+}
+
+// The info layouts leave the envelope bytes zeroed, so only the type is filled in
+impl Encode for SupportFunctionInfoResponse {
+    fn encode(&self) -> Vec<u8> {
+        let (info_type, mut bytes) = match self {
+            SupportFunctionInfoResponse::ImageSupportInfo(info) => (SupportFunctionInfoType::IMAGE_SUPPORT_INFO, info.encode()),
+            SupportFunctionInfoResponse::BatteryInfo(info) => (SupportFunctionInfoType::BATTERY_INFO, info.encode()),
+            SupportFunctionInfoResponse::PrinterFunctionInfo(info) => (SupportFunctionInfoType::PRINTER_FUNCTION_INFO, info.encode()),
+            SupportFunctionInfoResponse::PrintHistoryInfo(info) => (SupportFunctionInfoType::PRINT_HISTORY_INFO, info.encode()),
+            SupportFunctionInfoResponse::CameraFunctionInfo(info) => (SupportFunctionInfoType::CAMERA_FUNCTION_INFO, info.encode()),
+            SupportFunctionInfoResponse::CameraHistoryInfo(info) => (SupportFunctionInfoType::CAMERA_HISTORY_INFO, info.encode()),
+        };
+        bytes[1] = info_type as u8;
+        bytes
+    }
+}
+
+layout! {
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct ImageSupportInfo {
+        pub width: u16 = U16Be(2),
+        pub height: u16 = U16Be(4),
+        pub pic_type: u8 = U8(6),
+        pub pic_option: u8 = U8(7),
+        pub size: u32 = U32Be(8),
+    }
 }
 
 impl ImageSupportInfo {
-    pub fn from_bytes(bytes: &Vec<u8>) -> Self {
-        assert_eq!(bytes[1], SupportFunctionInfoType::IMAGE_SUPPORT_INFO as u8);
-        ImageSupportInfo {
-            width: u16::from_be_bytes([bytes[2], bytes[3]]),
-            height: u16::from_be_bytes([bytes[4], bytes[5]]),
-            pic_type: bytes[6],
-            pic_option: bytes[7],
-            size: u32::from_be_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]),
+    pub fn decode(bytes: &[u8], version: u8) -> Result<Self, DecodeError> {
+        match version {
+            1 => Decode::decode(bytes),
+            _ => Err(DecodeError::UnsupportedVersion { info_type: SupportFunctionInfoType::IMAGE_SUPPORT_INFO, version }),
         }
     }
 }
 
-#[derive(Debug)]
-pub struct BatteryInfo {
-    pub battery_level: u8,
-    pub battery_capacity: u8,
-    pub charger_type: u8,
-    pub charger_state: u8
+layout! {
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct BatteryInfo {
+        pub battery_level: u8 = U8(2),
+        pub battery_capacity: u8 = U8(3),
+        pub charger_type: u8 = U8(4),
+        pub charger_state: u8 = U8(5),
+    }
 }
 
 impl BatteryInfo {
-    pub fn from_bytes(bytes: &Vec<u8>) -> Self {
-        assert_eq!(bytes[1], SupportFunctionInfoType::BATTERY_INFO as u8);
-        BatteryInfo {
-            battery_level: bytes[2],
-            battery_capacity: bytes[3],
-            charger_type: bytes[4],
-            charger_state: bytes[5]
+    pub fn decode(bytes: &[u8], version: u8) -> Result<Self, DecodeError> {
+        match version {
+            1 => Decode::decode(bytes),
+            _ => Err(DecodeError::UnsupportedVersion { info_type: SupportFunctionInfoType::BATTERY_INFO, version }),
         }
     }
 }
 
-#[derive(Debug)]
-pub struct CameraFunctionInfo {
-    pub battery_level: u8,
-    pub is_charging: bool,
-    pub battery_capacity: u8,
-    pub auto_image_transfer_count: u8,
-    pub charger_state: u8,
-    pub camera_error_type: CameraErrorType,
-    pub camera_status: u8,
+// Unverified: inferred by analogy with CameraFunctionInfo, there is no capture
+// of a printer function info reply yet. Unknown states decode as UNKNOWN
+#[allow(non_camel_case_types)]
+#[derive(Debug, FromPrimitive, ToPrimitive, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PrinterState {
+    IDLE = 0,
+    PRINTING = 1,
+    NO_FILM = 2,
+    COVER_OPEN = 3,
+    PRINTER_ERROR = 4,
+    UNKNOWN = 255,
+}
+
+// Layout, guessed from the battery byte of CameraFunctionInfo:
+//   [2] bits 0-3: film remaining, bit 4: film pack loaded
+//   [3] PrinterState
+layout! {
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct PrinterFunctionInfo {
+        pub film_remaining: u8 = Bits(2, 0x0f),
+        pub is_film_loaded: bool = Flag(2, 0x10),
+        pub printer_state: PrinterState = EnumOr(3, PrinterState::UNKNOWN),
+    }
+}
+
+impl PrinterFunctionInfo {
+    pub fn decode(bytes: &[u8], version: u8) -> Result<Self, DecodeError> {
+        match version {
+            1 => Decode::decode(bytes),
+            _ => Err(DecodeError::UnsupportedVersion { info_type: SupportFunctionInfoType::PRINTER_FUNCTION_INFO, version }),
+        }
+    }
+}
+
+layout! {
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct PrintHistoryInfo {
+        pub total_print_num: u32 = U32Be(2),
+    }
+}
+
+impl PrintHistoryInfo {
+    pub fn decode(bytes: &[u8], version: u8) -> Result<Self, DecodeError> {
+        match version {
+            1 => Decode::decode(bytes),
+            _ => Err(DecodeError::UnsupportedVersion { info_type: SupportFunctionInfoType::PRINT_HISTORY_INFO, version }),
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, FromPrimitive, ToPrimitive, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChargerStateType {
+    NOT_CHARGING = 0,
+    CHARGING = 1,
+    CHARGE_COMPLETE = 2,
+    CHARGE_ERROR = 3,
+    UNKNOWN = 255,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, FromPrimitive, ToPrimitive, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CameraStatusType {
+    IDLE = 0,
+    SHOOTING = 1,
+    PLAYBACK = 2,
+    TRANSFERRING = 3,
+    PRINTING = 4,
+    SETTING = 5,
+    UNKNOWN = 255,
+}
+
+// Layout:
+//   [2] bits 0-3: battery level, bit 4: charging
+//   [3] battery capacity
+//   [4] images queued for automatic transfer
+//   [5] ChargerStateType
+//   [6..8] CameraErrorType, signed big endian
+//   [8] CameraStatusType, layout version 2 only
+// Unverified: byte 8 and the CameraStatusType codes are inferred from the
+// version bump, no version 2 reply has been captured yet
+layout! {
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct CameraFunctionInfo {
+        pub battery_level: u8 = Bits(2, 0x0f),
+        pub is_charging: bool = Flag(2, 0x10),
+        pub battery_capacity: u8 = U8(3),
+        pub auto_image_transfer_count: u8 = U8(4),
+        pub charger_state: ChargerStateType = EnumOr(5, ChargerStateType::UNKNOWN),
+        pub camera_error_type: CameraErrorType = ErrorCode(6),
+        pub camera_status: Option<CameraStatusType> = Optional(EnumOr(8, CameraStatusType::UNKNOWN)),
+    }
 }
 
 impl CameraFunctionInfo {
-    pub fn from_bytes(bytes: &Vec<u8>) -> Self {
-        assert_eq!(bytes[1], SupportFunctionInfoType::CAMERA_FUNCTION_INFO as u8);
-        CameraFunctionInfo {
-            battery_level: bytes[2] & 15,
-            is_charging: (bytes[2] << 4 & 1) != 0,
-            battery_capacity: bytes[3],
-            auto_image_transfer_count: bytes[4],
-            charger_state: bytes[5],
-            camera_error_type: FromPrimitive::from_u16(u16::from_be_bytes([bytes[6], bytes[7]])).unwrap(),
-            camera_status: bytes[8],
+    pub fn decode(bytes: &[u8], version: u8) -> Result<Self, DecodeError> {
+        match version {
+            1 => Ok(CameraFunctionInfo { camera_status: None, ..Decode::decode(bytes)? }),
+            2 => {
+                check_len(bytes, 9)?;
+                Decode::decode(bytes)
+            }
+            _ => Err(DecodeError::UnsupportedVersion { info_type: SupportFunctionInfoType::CAMERA_FUNCTION_INFO, version }),
         }
     }
 }
 
-#[derive(Debug)]
-pub struct CameraHistoryInfo {
-    pub total_shoot_num: u32,
+layout! {
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct CameraHistoryInfo {
+        pub total_shoot_num: u32 = U32Be(2),
+    }
 }
 
 impl CameraHistoryInfo {
-    pub fn from_bytes(bytes: &Vec<u8>) -> Self {
-        assert_eq!(bytes[1], SupportFunctionInfoType::CAMERA_HISTORY_INFO as u8);
-        CameraHistoryInfo {
-            total_shoot_num: u32::from_be_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]),
+    pub fn decode(bytes: &[u8], version: u8) -> Result<Self, DecodeError> {
+        match version {
+            1 => Decode::decode(bytes),
+            _ => Err(DecodeError::UnsupportedVersion { info_type: SupportFunctionInfoType::CAMERA_HISTORY_INFO, version }),
         }
     }
 }
 
-#[derive(Debug)]
-pub struct DateTimeResponse {
-    pub datestamp: String
+// TIME_SETTING
+// Layout: status(1) + time as text
+layout! {
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct DateTimeResponse {
+        pub status: ResponseStatus = Status(0),
+        pub datestamp: String = Text(1),
+    }
 }
 
-impl DateTimeResponse {
-    pub fn from_bytes(bytes: &Vec<u8>) -> Self {
-        DateTimeResponse {
-            datestamp: String::from_utf8_lossy(&bytes[1..]).into_owned()
-        }
+// PARAMETER_RW
+// Layout: status(1) + ReadWriteSettingMode(1) + ReadWriteSettingType(1) + value, little endian(4)
+// Unverified: the status byte is assumed like for the other replies, no
+// capture shows whether the mode or the status comes first
+layout! {
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct ParameterReadWriteResponse {
+        pub status: ResponseStatus = Status(0),
+        pub mode: ReadWriteSettingMode = Enum(1, "setting mode"),
+        pub setting: ReadWriteSettingType = Enum(2, "setting"),
+        pub value: u32 = U32Le(3),
     }
 }
 
-#[derive(Debug)]
-pub struct ParameterReadWriteResponse {
-    pub mode: ReadWriteSettingMode,
-    pub setting: ReadWriteSettingType,
-    pub value: u32
-}
-
-impl ParameterReadWriteResponse {
-    pub fn from_bytes(bytes: &Vec<u8>) -> Self {
-        ParameterReadWriteResponse {
-            mode: FromPrimitive::from_u8(bytes[0]).unwrap(),
-            setting: FromPrimitive::from_u8(bytes[1]).unwrap(),
-            value: u32::from_le_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]),
+// DEVICE_INFO_SERVICE
+// Layout: status(1) + DeviceInfoType(1) + value
+// Values mirror the BLE Device Information Service characteristics
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceInfo {
+    pub manufacturer_name: String,
+    pub model_number: String,
+    pub serial_number: String,
+    pub hw_revision: String,
+    pub fw_revision: String,
+    pub sw_revision: String,
+    #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
+    pub system_id: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
+    pub regulatory_data: Vec<u8>,
+    pub pnp_id: Option<PnpId>,
+    // Raw IDENTIFY_INFORMATION reply
+    #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
+    pub identify_information: Vec<u8>,
+}
+
+impl DeviceInfo {
+    // Store one DEVICE_INFO_SERVICE reply
+    // Fails on replies that do not decode or report anything but OK
+    pub fn update_from_bytes(&mut self, bytes: &[u8]) -> Result<(), CameraError> {
+        let entry = DeviceInfoEntry::decode(bytes)?;
+        if let Some(err) = CameraError::from_status(SID::DEVICE_INFO_SERVICE, entry.status) {
+            return Err(err);
+        }
+        let value = &entry.value[..];
+        let text = || String::from_utf8_lossy(value).trim_end_matches('\0').to_string();
+        match entry.info_type {
+            DeviceInfoType::MANUFACTURER_NAME => self.manufacturer_name = text(),
+            DeviceInfoType::MODEL_NUMBER => self.model_number = text(),
+            DeviceInfoType::SERIAL_NUMBER => self.serial_number = text(),
+            DeviceInfoType::HW_REVISION => self.hw_revision = text(),
+            DeviceInfoType::FW_REVISION => self.fw_revision = text(),
+            DeviceInfoType::SW_REVISION => self.sw_revision = text(),
+            DeviceInfoType::SYSTEM_ID => self.system_id = value.to_vec(),
+            DeviceInfoType::REGULATORY_DATA => self.regulatory_data = value.to_vec(),
+            DeviceInfoType::PNP_ID => self.pnp_id = PnpId::from_bytes(value),
         }
+        Ok(())
+    }
+}
+
+layout! {
+    // One DEVICE_INFO_SERVICE reply
+    #[derive(Debug, PartialEq)]
+    pub struct DeviceInfoEntry {
+        pub status: ResponseStatus = Status(0),
+        pub info_type: DeviceInfoType = Enum(1, "device info type"),
+        pub value: Vec<u8> = Bytes(2),
+    }
+}
+
+// PnP ID characteristic, little endian as defined by the BLE DIS specification
+layout! {
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct PnpId {
+        pub vendor_id_source: u8 = U8(0),
+        pub vendor_id: u16 = U16Le(1),
+        pub product_id: u16 = U16Le(3),
+        pub product_version: u16 = U16Le(5),
+    }
+}
+
+impl PnpId {
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Self::decode(bytes).ok()
+    }
+}
+
+// Byte fields are serialized as hex strings, as in the packet dumps
+#[cfg(feature = "serde")]
+mod hex_bytes {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::packet;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&packet::hex(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        packet::parse_hex(&text).ok_or_else(|| D::Error::custom("invalid hex string"))
+    }
+}
+
+// CHECK_CAMERA_STATUS
+// Layout: status(1) + status flags(1) + error(2)
+// Unverified: no capture of this reply yet, the leading status byte is assumed
+// like for the other replies
+layout! {
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct CameraStatus {
+        pub status: ResponseStatus = Status(0),
+        pub is_busy: bool = Flag(1, 0x01),
+        pub is_shooting: bool = Flag(1, 0x02),
+        pub is_printing: bool = Flag(1, 0x04),
+        pub error: CameraErrorType = ErrorCode(2),
+    }
+}
+
+impl CameraStatus {
+    pub fn is_ready(&self) -> bool {
+        !self.is_busy && !self.is_shooting && !self.is_printing
+    }
+}
+
+// URL_UPLOAD_INFO
+// Layout: status(1) + unknown(1) + picture size(4) + audio size(4)
+// Unverified: the status and unknown bytes are assumed like for SUPPORT_FUNCTION_INFO
+layout! {
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct UrlUploadInfo {
+        pub status: ResponseStatus = Status(0),
+        pub picture_size: u32 = U32Be(2),
+        pub audio_size: u32 = U32Be(6),
+    }
+}
+
+// CAMERA_LOG_*_START
+// Layout: status(1) + unknown(1) + log size(4)
+// Unverified: inferred from the URL upload replies, no camera log capture yet
+layout! {
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct CameraLogStartResponse {
+        pub status: ResponseStatus = Status(0),
+        pub size: u32 = U32Be(2),
+    }
+}
+
+// IMAGE_AUTO_UPLOAD_START
+// Layout: status(1) + unknown(2) + frame count(1)
+layout! {
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct AutoUploadStartResponse {
+        pub status: ResponseStatus = Status(0),
+        pub num_frames: u8 = U8(3),
+    }
+}
+
+// INTERVAL_RECORD_STATUS
+// Layout: status(1) + recording(1) + shots taken(2) + shots planned(2)
+// Unverified: the leading status byte is assumed like for the other replies
+layout! {
+    #[derive(Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct IntervalRecordStatus {
+        pub status: ResponseStatus = Status(0),
+        pub is_recording: bool = Bool(1),
+        pub shots_taken: u16 = U16Be(2),
+        pub shots_planned: u16 = U16Be(4),
+    }
+}
+
+// Request payloads
+// Layouts of what the client sends, so the same structs describe both sides of
+// every exchange.
+
+// SUPPORT_FUNCTION_INFO
+layout! {
+    #[derive(Debug, PartialEq)]
+    pub struct SupportFunctionInfoRequest {
+        pub info_type: SupportFunctionInfoType = Enum(0, "info type"),
+    }
+}
+
+// DEVICE_INFO_SERVICE
+layout! {
+    #[derive(Debug, PartialEq)]
+    pub struct DeviceInfoRequest {
+        pub info_type: DeviceInfoType = Enum(0, "device info type"),
+    }
+}
+
+// PARAMETER_RW
+// Layout: ReadWriteSettingType(1) + ReadWriteSettingMode(1) + value, little endian(4)
+// Reads send a zero value.
+layout! {
+    #[derive(Debug, PartialEq)]
+    pub struct ParameterReadWriteRequest {
+        pub setting: ReadWriteSettingType = Enum(0, "setting"),
+        pub mode: ReadWriteSettingMode = Enum(1, "setting mode"),
+        pub value: u32 = U32Le(2),
+    }
+}
+
+// CAMERA_SETTINGS
+// Layout: ReadWriteSettingType(1) + value, little endian(4)
+layout! {
+    #[derive(Debug, PartialEq)]
+    pub struct CameraSettingsRequest {
+        pub setting: ReadWriteSettingType = Enum(0, "setting"),
+        pub value: u32 = U32Le(1),
+    }
+}
+
+// AUTO_SLEEP_SETTINGS
+layout! {
+    #[derive(Debug, PartialEq)]
+    pub struct AutoSleepRequest {
+        pub minutes: u8 = U8(0),
+    }
+}
+
+// TIME_SETTING
+// Layout: mode(1) + time as text, %Y%m%d%H%M%S
+layout! {
+    #[derive(Debug, PartialEq)]
+    pub struct DateTimeRequest {
+        pub mode: u8 = U8(0),
+        pub datestamp: String = Text(1),
+    }
+}
+
+impl DateTimeRequest {
+    // Mode 2 sets the camera clock
+    pub fn set(time: chrono::DateTime<chrono::Utc>) -> Self {
+        DateTimeRequest { mode: 2, datestamp: time.format("%Y%m%d%H%M%S").to_string() }
+    }
+}
+
+// EXECUTE_CAMERA_COMMAND
+layout! {
+    #[derive(Debug, PartialEq)]
+    pub struct CameraCommandRequest {
+        pub command: CameraCommand = Enum(0, "camera command"),
+    }
+}
+
+// INTERVAL_RECORD_START
+// Layout: IntervalRecordMode(1) + shots(2)
+layout! {
+    #[derive(Debug, PartialEq)]
+    pub struct IntervalRecordStartRequest {
+        pub mode: IntervalRecordMode = Enum(0, "interval mode"),
+        pub shots: u16 = U16Be(1),
+    }
+}
+
+// Chunk requests of the upload and log transfers, answered with the index
+// followed by the chunk data
+layout! {
+    #[derive(Debug, PartialEq)]
+    pub struct ChunkRequest {
+        pub index: u32 = U32Be(0),
+    }
+}
+
+// URL_UPLOAD_ADDRESS
+layout! {
+    #[derive(Debug, PartialEq)]
+    pub struct UrlUploadAddress {
+        pub url: String = Text(0),
     }
 }
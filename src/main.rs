@@ -1,317 +1,827 @@
 use std::error::Error;
-use std::pin::Pin;
-use std::thread;
+use std::fmt;
+use std::process::ExitCode;
 use std::time::Duration;
-use std::process::exit;
-use std::fs;
+use std::fs::{self, OpenOptions};
 use std::path::Path;
-use bluer::{gatt::remote::Characteristic, Uuid};
-use futures::{Stream, StreamExt};
-use num_traits::FromPrimitive;
+use std::env;
+use std::io::{self, Write};
+use bluer::Address;
 use chrono::prelude::*;
+use tokio::time::sleep;
 use instax_pal::*;
+use instax_pal::battery::{BatteryMonitor, BatterySample};
+use instax_pal::camera::{Camera, CameraError};
+use instax_pal::console::Console;
+use instax_pal::discovery::{self, ConnectOptions, DeviceSelector};
+use instax_pal::manager::CameraManager;
+use instax_pal::packet::{self, Packet};
+use num_traits::FromPrimitive;
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+use instax_pal::profile::Profile;
+use instax_pal::recording::{Recording, SessionRecorder};
+use instax_pal::upload::LocalFileBackend;
+
+const USAGE: &str = "usage: instax_pal [options] <command> [args]
 
-// UART-like GATT service
-// Commands are sent to INSTAX_WRITE_UUID characteristic
-// Responses are read from INSTAX_NOTIFY_UUID characteristic
-// Reference: https://github.com/jpwsutton/instax_api/issues/21#issuecomment-770462168
-const INSTAX_SERVICE_UUID: Uuid = Uuid::from_u128(0x70954782_2d83_473d_9e5f_81e1d02d5273);
-const INSTAX_WRITE_UUID: Uuid = Uuid::from_u128(0x70954783_2d83_473d_9e5f_81e1d02d5273);
-const INSTAX_NOTIFY_UUID: Uuid = Uuid::from_u128(0x70954784_2d83_473d_9e5f_81e1d02d5273);
+options:
+  --adapter <name>           Bluetooth adapter, e.g. hci1
+  --device <address|alias>   camera to use when several are around
+  --scan-timeout <seconds>   how long to scan for cameras (default 5)
+  --pair                     pair and trust the camera before connecting
+  --output <text|json>       output format (default text)
+  --record <file>            record every frame of the session to a file
+  -v, --verbose              log decoded packets, twice for every field and raw bytes
+  -q, --quiet                log warnings and errors only
+  RUST_LOG overrides the log filter, e.g. RUST_LOG=instax_pal::camera=trace
+
+commands:
+  scan [--watch | --adapters]
+  pair
+  info
+  status
+  settings list | get <name> | set <name> <value>
+  sync-time
+  download [<directory>]
+  shutter | print | cancel
+  live-view [<file>]
+  logs <subtotal|date|filter|record-date> [<file>]
+  raw <sid> [<hex data>]
+  console [<script>]
+  decode <btsnoop_hci.log|recording> [--save <recording>]
+  power off [--yes] | reset [--yes] | sleep <minutes>
+  profile export <file> | import <file> | diff <file|address> [<file|address>]
+  interval start | status | stop | download | run
+  sound-print <base url> [<directory>]
+  monitor [--interval <seconds>] [--threshold <level>]... [--log <file>]
+  fleet status | sync-time | apply <file>";
+
+// Exit codes, so scripts can tell failures apart
+const EXIT_FAILURE: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_NOT_FOUND: u8 = 3;
+const EXIT_CONNECTION: u8 = 4;
+const EXIT_UNSUPPORTED: u8 = 5;
+const EXIT_DEVICE: u8 = 6;
+const EXIT_TIMEOUT: u8 = 7;
+const EXIT_PROTOCOL: u8 = 8;
 
 #[derive(Debug)]
-enum PacketType {
-    Sid = 0,
-    Type = 1,
-    Data = 3,
+struct UsageError(String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
-#[derive(Debug)]
-pub struct Packet {
-    p_type: PacketType,
-    direction: Direction,
-    size: u16,
-    sid: SID,
-    msg_type: u8,
-    data: Vec<u8>
-}
-impl Packet {
-    pub fn pack(&self) -> Vec<u8> {
-        let mut packet: Vec<u8> = Vec::new();
-        packet.extend((self.direction as u16).to_be_bytes());
-        packet.extend(self.size.to_be_bytes());
-        packet.extend((self.sid as u16).to_be_bytes());
-        if matches!(self.p_type, PacketType::Type) {
-            packet.push(self.msg_type);
+impl Error for UsageError {}
+
+fn usage_error(usage: &str) -> Box<dyn Error> {
+    Box::new(UsageError(usage.to_string()))
+}
+
+fn exit_code(err: &(dyn Error + 'static)) -> u8 {
+    if err.is::<UsageError>() {
+        return EXIT_USAGE;
+    }
+    match err.downcast_ref::<CameraError>() {
+        Some(CameraError::NotFound(_)) => EXIT_NOT_FOUND,
+        Some(CameraError::Bluetooth(_) | CameraError::ConnectionFailed(_) | CameraError::Disconnected | CameraError::TransferInterrupted(_)) => EXIT_CONNECTION,
+        Some(CameraError::Unsupported(_)) => EXIT_UNSUPPORTED,
+        Some(CameraError::Device(_) | CameraError::Busy(_) | CameraError::NoData(_) | CameraError::InvalidParameter(_) | CameraError::Rejected(..) | CameraError::NotApplied { .. } | CameraError::PhotosQueued { .. }) => EXIT_DEVICE,
+        Some(CameraError::Timeout | CameraError::NoResponse(_)) => EXIT_TIMEOUT,
+        Some(CameraError::Decode(_) | CameraError::Replay(_)) => EXIT_PROTOCOL,
+        None => EXIT_FAILURE,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+// Commands that can print JSON
+const JSON_COMMANDS: [&str; 7] = ["scan", "info", "status", "settings", "raw", "monitor", "fleet"];
+
+#[cfg(feature = "serde")]
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), Box<dyn Error>> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+// One JSON document per line, for streaming commands
+#[cfg(feature = "serde")]
+fn print_json_line<T: serde::Serialize>(value: &T) -> Result<(), Box<dyn Error>> {
+    println!("{}", serde_json::to_string(value)?);
+    Ok(())
+}
+
+// --output json is rejected before any command runs in builds without serde
+#[cfg(not(feature = "serde"))]
+fn print_json<T>(_value: &T) -> Result<(), Box<dyn Error>> {
+    Err(usage_error("built without JSON support, enable the serde feature"))
+}
+
+#[cfg(not(feature = "serde"))]
+fn print_json_line<T>(value: &T) -> Result<(), Box<dyn Error>> {
+    print_json(value)
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(env::args().skip(1).collect()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::from(exit_code(err.as_ref()))
         }
-        if matches!(self.p_type, PacketType::Data) {
-            packet.extend(&self.data);
+    }
+}
+
+async fn run(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let (global, args) = parse_global_options(args)?;
+    init_logging(global.verbosity);
+    let options = &global.connect;
+    let output = global.output;
+    let command = args.first().map_or("help", String::as_str);
+    let args = &args[args.len().min(1)..];
+    if output == OutputFormat::Json && !JSON_COMMANDS.contains(&command) {
+        return Err(usage_error(&format!("--output json is not supported by {}", command)));
+    }
+    match command {
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
         }
-        // Add checksum
-        let checksum: u8 = 255 - packet.iter().fold(0, |a: u8, &b| a.wrapping_add(b));
-        packet.push(checksum);
-        packet
-    }
-    pub fn unpack(msg: &Vec<u8>) -> Self {
-        let p_type = match msg.len() {
-            0..=7 => panic!("ERROR: Packet too short. len:{}", msg.len()),
-            8 => PacketType::Sid,
-            9 => PacketType::Type,
-            _ => PacketType::Data,
-        };
-        match p_type {
-            PacketType::Sid => {
-                let direction = FromPrimitive::from_u16(u16::from_be_bytes(msg[0..2].try_into().unwrap())).unwrap();
-                let size = u16::from_be_bytes(msg[2..4].try_into().unwrap());
-                let sid = FromPrimitive::from_u16(u16::from_be_bytes(msg[4..6].try_into().unwrap())).unwrap();
-                let msg_type = msg[6];
-                let data = Vec::new();
-                Packet{p_type, direction, size, sid, msg_type, data}
+        "scan" => scan_command(options, output, args).await,
+        "pair" => pair_command(options).await,
+        "info" => info_command(options, output).await,
+        "status" => status_command(options, output).await,
+        "settings" => settings_command(options, output, args).await,
+        "sync-time" => sync_time_command(options).await,
+        "download" => download_command(options, args).await,
+        "shutter" => camera_command(options, CameraCommand::SHUTTER).await,
+        "print" => camera_command(options, CameraCommand::PRINT).await,
+        "cancel" => camera_command(options, CameraCommand::CANCEL).await,
+        "live-view" => live_view_command(options, args).await,
+        "logs" => logs_command(options, args).await,
+        "raw" => raw_command(options, output, args).await,
+        "console" => console_command(options, args).await,
+        "decode" => decode_command(args),
+        "power" => power_command(options, args).await,
+        "profile" => profile_command(options, args).await,
+        "interval" => interval_command(options, args).await,
+        "sound-print" => sound_print_command(options, args).await,
+        "monitor" => monitor_command(options, output, args).await,
+        "fleet" => fleet_command(options, output, args).await,
+        _ => Err(usage_error(USAGE)),
+    }
+}
+
+// Global options, given before the command
+struct GlobalOptions {
+    connect: ConnectOptions,
+    output: OutputFormat,
+    // -1 quiet, 0 default, 1 debug, 2 and up trace
+    verbosity: i8,
+}
+
+fn parse_global_options(args: Vec<String>) -> Result<(GlobalOptions, Vec<String>), Box<dyn Error>> {
+    let mut options = ConnectOptions::default();
+    let mut output = OutputFormat::Text;
+    let mut verbosity: i8 = 0;
+    let mut quiet = false;
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next_if(|arg| arg.starts_with('-') && arg != "--help" && arg != "-h") {
+        let mut value = || args.next().ok_or_else(|| usage_error(&format!("{} needs a value", arg)));
+        match arg.as_str() {
+            "--pair" => options.pair = true,
+            "--verbose" => verbosity = verbosity.saturating_add(1),
+            // -v, -vv, -vvv, ...
+            short if short.len() > 1 && short[1..].chars().all(|c| c == 'v') => {
+                verbosity = verbosity.saturating_add((short.len() - 1).min(i8::MAX as usize) as i8);
+            }
+            "-q" | "--quiet" => quiet = true,
+            "--adapter" => options.adapter = Some(value()?),
+            "--record" => options.record = Some(SessionRecorder::create(Path::new(&value()?))?),
+            "--device" => options.device = value()?.parse()?,
+            "--scan-timeout" => {
+                let seconds = value()?.parse().map_err(|_| usage_error("--scan-timeout needs a number of seconds"))?;
+                options.scan_timeout = Duration::from_secs(seconds);
+            }
+            "--output" => output = match value()?.as_str() {
+                "text" => OutputFormat::Text,
+                "json" if cfg!(feature = "serde") => OutputFormat::Json,
+                "json" => return Err(usage_error("built without JSON support, enable the serde feature")),
+                _ => return Err(usage_error("--output must be text or json")),
+            },
+            _ => return Err(usage_error(&format!("unknown option {}\n{}", arg, USAGE))),
+        }
+    }
+    if quiet && verbosity > 0 {
+        return Err(usage_error("--quiet and --verbose cannot be combined"));
+    }
+    if quiet {
+        verbosity = -1;
+    }
+    Ok((GlobalOptions { connect: options, output, verbosity }, args.collect()))
+}
+
+// Logs go to stderr so command output on stdout stays machine readable
+fn init_logging(verbosity: i8) {
+    let default_filter = match verbosity {
+        i8::MIN..=-1 => "warn",
+        0 => "info",
+        1 => "info,instax_pal=debug",
+        _ => "info,instax_pal=trace",
+    };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_filter));
+    tracing_subscriber::fmt().with_env_filter(filter).with_writer(io::stderr).init();
+}
+
+// settings list
+// settings get <name>
+// settings set <name> <value>
+async fn settings_command(options: &ConnectOptions, output: OutputFormat, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: settings list | get <name> | set <name> <value>";
+    let setting = |name: &String| {
+        ReadWriteSettingType::from_name(&name.to_uppercase())
+            .ok_or_else(|| usage_error(&format!("unknown setting {}", name)))
+    };
+    match (args.first().map(String::as_str), args.get(1), args.get(2)) {
+        (Some("list"), None, None) => {
+            let mut camera = Camera::connect(options).await?;
+            let mut responses = Vec::new();
+            for setting in ReadWriteSettingType::ALL {
+                match camera.parameter_read(setting).await {
+                    Ok(response) => responses.push(response),
+                    Err(err) => warn!(setting = setting.name(), error = %err, "cannot read setting"),
+                }
             }
-            PacketType::Type => {
-                let direction = FromPrimitive::from_u16(u16::from_be_bytes(msg[0..2].try_into().unwrap())).unwrap();
-                let size = u16::from_be_bytes(msg[2..4].try_into().unwrap());
-                let sid = FromPrimitive::from_u16(u16::from_be_bytes(msg[4..6].try_into().unwrap())).unwrap();
-                let msg_type = msg[6];
-                let data: Vec<u8> = msg[7..((msg.len() - 1) as usize)].to_vec();
-                Packet{p_type, direction, size, sid, msg_type, data}
+            match output {
+                OutputFormat::Json => print_json(&responses)?,
+                OutputFormat::Text => {
+                    for response in &responses {
+                        println!("{} = {}", response.setting.name(), response.value);
+                    }
+                }
             }
-            PacketType::Data => {
-                let direction = FromPrimitive::from_u16(u16::from_be_bytes(msg[0..2].try_into().unwrap())).unwrap();
-                let size = u16::from_be_bytes(msg[2..4].try_into().unwrap());
-                let sid = FromPrimitive::from_u16(u16::from_be_bytes(msg[4..6].try_into().unwrap())).unwrap();
-                let msg_type = 0;
-                let data: Vec<u8> = msg[6..((msg.len() - 1) as usize)].to_vec();
-                Packet{p_type, direction, size, sid, msg_type, data}
+        }
+        (Some("get"), Some(name), None) => {
+            let setting = setting(name)?;
+            let response = Camera::connect(options).await?.parameter_read(setting).await?;
+            match output {
+                OutputFormat::Json => print_json(&response)?,
+                OutputFormat::Text => println!("{}", response.value),
             }
         }
+        (Some("set"), Some(name), Some(value)) => {
+            let setting = setting(name)?;
+            let value = value.parse().map_err(|_| usage_error(usage))?;
+            Camera::connect(options).await?.parameter_write(setting, value).await?;
+        }
+        _ => return Err(usage_error(usage)),
+    }
+    Ok(())
+}
+
+async fn sync_time_command(options: &ConnectOptions) -> Result<(), Box<dyn Error>> {
+    let mut camera = Camera::connect(options).await?;
+    camera.set_timedate().await?;
+    println!("Camera time set to {}", Local::now().format("%Y-%m-%d %H:%M:%S"));
+    Ok(())
+}
+
+// download [<directory>]
+// Saves every photo queued for automatic transfer
+async fn download_command(options: &ConnectOptions, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let directory = Path::new(args.first().map_or(".", String::as_str));
+    fs::create_dir_all(directory)?;
+    let mut camera = Camera::connect(options).await?;
+    let images = camera.automatic_photo_download().await?;
+    let prefix = Local::now().format("instax_%Y%m%d_%H%M%S");
+    for (i, image) in images.iter().enumerate() {
+        let file = directory.join(format!("{}_{}.jpg", prefix, i + 1));
+        fs::write(&file, image)?;
+        println!("{}", file.display());
     }
-    fn with_sid(sid: SID) -> Self {
-        Packet {
-            p_type: PacketType::Sid,
-            direction: Direction::TO,
-            size: 7, // Direction(2) + Size (2) + SID (2) + Checksum (1)
-            sid,
-            msg_type: 0,
-            data: vec![],
+    println!("Downloaded {} photos", images.len());
+    Ok(())
+}
+
+// shutter | print | cancel
+async fn camera_command(options: &ConnectOptions, command: CameraCommand) -> Result<(), Box<dyn Error>> {
+    let mut camera = Camera::connect(options).await?;
+    if command != CameraCommand::CANCEL {
+        camera.wait_until_ready(Duration::from_secs(30)).await?;
+    }
+    camera.execute_camera_command(command).await?;
+    Ok(())
+}
+
+// live-view [<file>]
+async fn live_view_command(options: &ConnectOptions, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let file = args.first().map_or("liveview.jpg", String::as_str);
+    let mut camera = Camera::connect(options).await?;
+    fs::write(file, camera.live_view_image().await?)?;
+    println!("{}", file);
+    Ok(())
+}
+
+// logs <subtotal|date|filter|record-date> [<file>]
+// Writes the raw log to the file, or a hex dump to stdout
+async fn logs_command(options: &ConnectOptions, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: logs <subtotal|date|filter|record-date> [<file>]";
+    let log = match args.first().map(String::as_str) {
+        Some("subtotal") => CameraLogType::SUBTOTAL,
+        Some("date") => CameraLogType::DATE,
+        Some("filter") => CameraLogType::FILTER,
+        Some("record-date") => CameraLogType::RECORD_DATE,
+        _ => return Err(usage_error(usage)),
+    };
+    let data = Camera::connect(options).await?.camera_log(log).await?;
+    match args.get(1) {
+        Some(file) => fs::write(file, &data)?,
+        None => {
+            for line in data.chunks(16) {
+                println!("{}", packet::hex(line));
+            }
+        }
+    }
+    Ok(())
+}
+
+// raw <sid> [<hex data>]
+// Sends one packet and prints the response
+async fn raw_command(options: &ConnectOptions, output: OutputFormat, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: raw <sid> [<hex data>], e.g. raw 8500 or raw 0x0002 02";
+    let sid = args.first()
+        .and_then(|sid| u16::from_str_radix(sid.trim_start_matches("0x"), 16).ok())
+        .ok_or_else(|| usage_error(usage))?;
+    let sid = SID::from_u16(sid).ok_or_else(|| usage_error(&format!("unknown SID {:04x}", sid)))?;
+    let data = match args.get(1) {
+        Some(data) => packet::parse_hex(data).ok_or_else(|| usage_error(usage))?,
+        None => Vec::new(),
+    };
+    let mut camera = Camera::connect(options).await?;
+    let response = camera.request(Packet::with_data(sid, data)).await?;
+    match output {
+        OutputFormat::Json => print_json(&response)?,
+        OutputFormat::Text => println!("{:?} {}", response.sid, packet::hex(&response.data)),
+    }
+    Ok(())
+}
+
+// console [<script>]
+// Reads packets to send from stdin, after running the script if given
+async fn console_command(options: &ConnectOptions, args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.len() > 1 {
+        return Err(usage_error("usage: console [<script>]"));
+    }
+    let mut camera = Camera::connect(options).await?;
+    let mut console = Console::new(&mut camera);
+    let mut out = io::stdout();
+    if let Some(script) = args.first() {
+        if !console.execute(&format!("run {}", script), &mut out).await? {
+            return Ok(());
+        }
+    }
+    println!("Type help for commands, quit to leave");
+    loop {
+        print!("> ");
+        out.flush()?;
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 || !console.execute(&line, &mut out).await? {
+            return Ok(());
         }
     }
-    fn with_type(sid: SID, msg_type: u8) -> Self {
-        Packet {
-            p_type: PacketType::Type,
-            direction: Direction::TO,
-            size: 8, // Direction(2) + Size (2) + SID (2) + Type (1) + Checksum (1)
-            sid,
-            msg_type,
-            data: vec![],
+}
+
+// decode <btsnoop_hci.log|recording> [--save <recording>]
+// Prints an annotated timeline of a captured session, no camera needed.
+// btsnoop logs can be saved as recordings for replay.
+fn decode_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: decode <btsnoop_hci.log|recording> [--save <recording>]";
+    let (file, save) = match args {
+        [file] => (file, None),
+        [file, option, save] if option == "--save" => (file, Some(save)),
+        _ => return Err(usage_error(usage)),
+    };
+    let bytes = fs::read(file)?;
+    let recording = if btsnoop::is_btsnoop(&bytes) {
+        btsnoop::import(&bytes)?
+    } else {
+        Recording::from_text(&String::from_utf8_lossy(&bytes))?
+    };
+    for entry in timeline::annotate(&recording) {
+        println!("{}", entry);
+    }
+    if let Some(save) = save {
+        fs::write(save, recording.to_text())?;
+    }
+    Ok(())
+}
+
+// scan [--adapters | --watch]
+// Lists cameras advertising the Instax service with their advertised state
+async fn scan_command(options: &ConnectOptions, output: OutputFormat, args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.iter().any(|arg| arg == "--adapters") {
+        let names = discovery::adapter_names().await?;
+        match output {
+            OutputFormat::Json => print_json(&names)?,
+            OutputFormat::Text => {
+                for name in names {
+                    println!("{}", name);
+                }
+            }
         }
+        return Ok(());
+    }
+    let session = bluer::Session::new().await?;
+    let adapter = discovery::open_adapter(&session, options.adapter.as_deref()).await?;
+    if args.iter().any(|arg| arg == "--watch") {
+        info!(adapter = adapter.name(), "watching, press Ctrl-C to stop");
+        let mut result = Ok(());
+        discovery::watch(&adapter, Duration::from_secs(2), |cameras| {
+            let cameras: Vec<_> = cameras.iter().filter(|camera| options.device.matches(camera)).collect();
+            match output {
+                OutputFormat::Json => result = print_json_line(&cameras),
+                OutputFormat::Text => {
+                    println!("-- {}", Local::now().format("%H:%M:%S"));
+                    for camera in cameras {
+                        println!("{}", camera);
+                    }
+                }
+            }
+            result.is_ok()
+        }).await?;
+        return result;
     }
-    fn with_data(sid: SID, data: Vec<u8>) -> Self {
-        Packet {
-            p_type: PacketType::Data,
-            direction: Direction::TO,
-            size: 7 + data.len() as u16, // Direction(2) + Size (2) + SID (2) + Payload (N) + Checksum (1)
-            sid,
-            msg_type: 0,
-            data: data,
+    info!(adapter = adapter.name(), seconds = options.scan_timeout.as_secs(), "scanning");
+    let cameras: Vec<_> = discovery::scan(&adapter, options.scan_timeout).await?
+        .into_iter()
+        .filter(|camera| options.device.matches(camera))
+        .collect();
+    match output {
+        OutputFormat::Json => print_json(&cameras)?,
+        OutputFormat::Text => {
+            if cameras.is_empty() {
+                println!("No cameras found");
+            }
+            for camera in &cameras {
+                println!("{}", camera);
+            }
         }
     }
+    Ok(())
 }
 
-pub struct Camera {
-    write_characteristic: Characteristic,
-    notify_stream: Pin<Box<dyn Stream<Item = Vec<u8>>>>
+// pair
+// Pairs and trusts the selected camera
+async fn pair_command(options: &ConnectOptions) -> Result<(), Box<dyn Error>> {
+    let session = bluer::Session::new().await?;
+    let adapter = discovery::open_adapter(&session, options.adapter.as_deref()).await?;
+    let device = discovery::find_camera(&adapter, &options.device, options.scan_timeout).await?;
+    discovery::pair(&session, &device).await?;
+    println!("Paired with {}", device.address());
+    Ok(())
 }
 
-impl Camera {
-    pub async fn new() -> Result<Self, Box<dyn Error>> {
-        let session = bluer::Session::new().await?;
-        let adapter = session.default_adapter().await?;
-        let mut device = None;
-        for address in adapter.device_addresses().await.unwrap() {
-            let dev = adapter.device(address).unwrap();
-            let uuids = dev.uuids().await.unwrap().unwrap();
-            if uuids.contains(&INSTAX_SERVICE_UUID) {
-                device = Some(dev);
+// fleet status | sync-time | apply <file>
+// Runs on every camera found, or those matching --device
+async fn fleet_command(options: &ConnectOptions, output: OutputFormat, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: fleet status | sync-time | apply <file>";
+    if options.record.is_some() {
+        return Err(usage_error("--record needs a single camera and cannot be used with fleet"));
+    }
+    let results = match (args.first().map(String::as_str), args.get(1)) {
+        (Some("status"), None) => {
+            let mut manager = CameraManager::connect_all(options).await?;
+            let summaries = manager.status().await;
+            if output == OutputFormat::Json {
+                return print_json(&summaries);
             }
-        };
-        let device = device.expect("Instax camera not found. Pair using bluetooth settings");
-        device.connect().await?;
-        if device.is_connected().await? {
-            println!("Connected to Instax camera");
+            for summary in &summaries {
+                println!("{}", summary);
+            }
+            let problems = summaries.iter().filter(|summary| summary.has_problem()).count();
+            println!("{} cameras, {} with problems", summaries.len(), problems);
+            return Ok(());
         }
-        else {
-            println!("Cannot connect to camera");
-            exit(1);
+        _ if output == OutputFormat::Json => return Err(usage_error("--output json is only supported by fleet status")),
+        (Some("sync-time"), None) => CameraManager::connect_all(options).await?.set_timedate_all().await,
+        (Some("apply"), Some(file)) => {
+            let profile = Profile::load(Path::new(file))?;
+            CameraManager::connect_all(options).await?.apply_profile_all(&profile).await
         }
-        let mut instax_service = None;
-        let mut write_characteristic = None;
-        let mut notify_characteristic = None;
-        for service in device.services().await.unwrap() {
-            if service.uuid().await? == INSTAX_SERVICE_UUID {
-                instax_service = Some(service);
+        _ => return Err(usage_error(usage)),
+    };
+    let mut failed = 0;
+    for (alias, result) in results {
+        match result {
+            Ok(()) => println!("{}: ok", alias),
+            Err(err) => {
+                println!("{}: {}", alias, err);
+                failed += 1;
             }
         }
-        let instax_service = instax_service.expect("Instax BLE service not found");
-        for characteristic in instax_service.characteristics().await.unwrap() {
-            match characteristic.uuid().await? {
-                INSTAX_WRITE_UUID => { write_characteristic = Some(characteristic); }
-                INSTAX_NOTIFY_UUID => { notify_characteristic = Some(characteristic); }
-                _ => {}
-            };
+    }
+    if failed > 0 {
+        return Err(format!("failed on {} cameras", failed).into());
+    }
+    Ok(())
+}
+
+// status: battery, printer and print history in one report
+async fn status_command(options: &ConnectOptions, output: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let mut camera = Camera::connect(options).await?;
+    let info_types = [
+        SupportFunctionInfoType::BATTERY_INFO,
+        SupportFunctionInfoType::PRINTER_FUNCTION_INFO,
+        SupportFunctionInfoType::PRINT_HISTORY_INFO,
+    ];
+    if output == OutputFormat::Json {
+        // Unsupported info types are left out
+        let mut responses = Vec::new();
+        for info_type in info_types {
+            match camera.support_function_info(info_type).await {
+                Ok(info) => responses.push(info),
+                Err(CameraError::Unsupported(_)) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        return print_json(&responses);
+    }
+    for info_type in info_types {
+        match camera.support_function_info(info_type).await {
+            Ok(SupportFunctionInfoResponse::BatteryInfo(info)) => {
+                println!("Battery: {}% (capacity {}, charger state {})", info.battery_level, info.battery_capacity, info.charger_state);
+            }
+            Ok(SupportFunctionInfoResponse::PrinterFunctionInfo(info)) => {
+                println!("Film remaining: {}", info.film_remaining);
+                println!("Printer state: {:?}", info.printer_state);
+            }
+            Ok(SupportFunctionInfoResponse::PrintHistoryInfo(info)) => {
+                println!("Total prints: {}", info.total_print_num);
+            }
+            Ok(info) => return Err(format!("Unexpected response {:?}", info).into()),
+            Err(CameraError::Unsupported(function)) => println!("{}: not supported", function),
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(())
+}
+
+// monitor [--interval <seconds>] [--threshold <level>]... [--log <file>]
+// Polls CAMERA_FUNCTION_INFO, reports battery events and appends readings to a CSV history
+async fn monitor_command(options: &ConnectOptions, output: OutputFormat, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: monitor [--interval <seconds>] [--threshold <level>]... [--log <file>]";
+    let mut interval = Duration::from_secs(4);
+    let mut thresholds = Vec::new();
+    let mut log_path = "battery_history.csv".to_string();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| usage_error(usage))?;
+        match arg.as_str() {
+            "--interval" => interval = Duration::from_secs(value.parse()?),
+            "--threshold" => thresholds.push(value.parse()?),
+            "--log" => log_path = value.clone(),
+            _ => return Err(usage_error(usage)),
+        }
+    }
+    if thresholds.is_empty() {
+        thresholds = vec![2, 1];
+    }
+    let mut log = OpenOptions::new().create(true).append(true).open(&log_path)?;
+    if log.metadata()?.len() == 0 {
+        writeln!(log, "{}", BatterySample::CSV_HEADER)?;
+    }
+    let mut camera = Camera::connect(options).await?;
+    let mut monitor = BatteryMonitor::new(thresholds);
+    loop {
+        match camera.support_function_info(SupportFunctionInfoType::CAMERA_FUNCTION_INFO).await? {
+            SupportFunctionInfoResponse::CameraFunctionInfo(info) => {
+                let sample = BatterySample::new(&info);
+                writeln!(log, "{}", sample.to_csv())?;
+                let time = sample.time;
+                for event in monitor.update(sample) {
+                    match output {
+                        OutputFormat::Json => print_json_line(&(time, event))?,
+                        OutputFormat::Text => println!("{}: {:?}", time.format("%H:%M:%S"), event),
+                    }
+                }
+            }
+            info => return Err(format!("Unexpected response {:?}", info).into()),
         }
-        let write_characteristic = write_characteristic.unwrap();
-        let notify_characteristic = notify_characteristic.unwrap();
-        let notify_stream: Pin<Box<dyn Stream<Item = Vec<u8>>>> = Box::pin(notify_characteristic.notify().await?);
-        Ok(Self{write_characteristic, notify_stream})
+        sleep(interval).await;
     }
+}
 
-    pub async fn send_data(&self, data: Vec<u8>) -> Result<(), Box<dyn Error>> {
-        println!("SENT: {:x?}", &data);
-        self.write_characteristic.write(&data).await?;
-        Ok(())
+async fn info_command(options: &ConnectOptions, output: OutputFormat) -> Result<(), Box<dyn Error>> {
+    let mut camera = Camera::connect(options).await?;
+    let info = camera.device_info().await?;
+    match output {
+        OutputFormat::Json => print_json(&info)?,
+        OutputFormat::Text => print_device_info(&info),
     }
+    Ok(())
+}
 
-    pub async fn receive_data(&mut self) -> Option<Vec<u8>> {
-        let data = self.notify_stream.next().await?;
-        println!("RECV: {:x?}", &data);
-        Some(data)
+fn print_device_info(info: &DeviceInfo) {
+    println!("Manufacturer: {}", info.manufacturer_name);
+    println!("Model: {}", info.model_number);
+    println!("Serial number: {}", info.serial_number);
+    println!("Hardware revision: {}", info.hw_revision);
+    println!("Firmware revision: {}", info.fw_revision);
+    println!("Software revision: {}", info.sw_revision);
+    println!("System ID: {}", packet::hex(&info.system_id));
+    println!("Regulatory data: {}", packet::hex(&info.regulatory_data));
+    match &info.pnp_id {
+        Some(pnp_id) => println!(
+            "PnP ID: source {} vendor {:#06x} product {:#06x} version {:#06x}",
+            pnp_id.vendor_id_source, pnp_id.vendor_id, pnp_id.product_id, pnp_id.product_version
+        ),
+        None => println!("PnP ID: -"),
     }
+    println!("Identify information: {}", packet::hex(&info.identify_information));
+}
+
+fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok() && answer.trim().eq_ignore_ascii_case("y")
+}
 
-    pub async fn send_packet(&self, packet: Packet) -> Result<(), Box<dyn Error>> {
-        let data = packet.pack();
-        self.send_data(data).await
+// power off [--yes]
+// power reset [--yes]
+// power sleep <minutes>
+async fn power_command(options: &ConnectOptions, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: power off [--yes] | reset [--yes] | sleep <minutes>";
+    let confirmed = |question: &str| args.iter().any(|arg| arg == "--yes") || confirm(question);
+    match args.first().map(String::as_str) {
+        Some("off") => {
+            if !confirmed("Shut down the camera?") {
+                return Ok(());
+            }
+            Camera::connect(options).await?.shut_down().await?;
+        }
+        Some("reset") => {
+            if !confirmed("Reset the camera?") {
+                return Ok(());
+            }
+            Camera::connect(options).await?.reset().await?;
+        }
+        Some("sleep") => {
+            let minutes = args.get(1).and_then(|minutes| minutes.parse().ok()).ok_or_else(|| usage_error(usage))?;
+            Camera::connect(options).await?.set_auto_sleep(minutes).await?;
+        }
+        _ => return Err(usage_error(usage)),
     }
+    Ok(())
+}
 
-    pub async fn receive_packet(&mut self) -> Option<Packet> {
-        let data = self.receive_data().await?;
-        Some(Packet::unpack(&data))
+// A profile source is either a camera address or a profile file
+async fn load_profile(options: &ConnectOptions, source: Option<&String>) -> Result<Profile, Box<dyn Error>> {
+    match source {
+        None => Ok(Camera::connect(options).await?.read_profile().await?),
+        Some(source) => match source.parse::<Address>() {
+            Ok(address) => {
+                let options = ConnectOptions { device: DeviceSelector::Address(address), ..options.clone() };
+                Ok(Camera::connect(&options).await?.read_profile().await?)
+            }
+            Err(_) => Ok(Profile::load(Path::new(source))?),
+        },
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let mut camera = Camera::new().await?;
-    support_function_version_info(&mut camera).await;
-    parameter_read(&mut camera, ReadWriteSettingType::TRANSFER_FORMAT).await;
-    parameter_read(&mut camera, ReadWriteSettingType::FLASH_SETTING).await;
-    set_timedate(&mut camera).await;
-    support_function_info(&mut camera, SupportFunctionInfoType::IMAGE_SUPPORT_INFO).await;
-    support_function_info(&mut camera, SupportFunctionInfoType::BATTERY_INFO).await;
-    support_function_info(&mut camera, SupportFunctionInfoType::CAMERA_FUNCTION_INFO).await;
-    support_function_info(&mut camera, SupportFunctionInfoType::CAMERA_HISTORY_INFO).await;
-    //automatic_photo_download(&mut camera).await;
-    live_view_test(&mut camera).await;
-    keepalive(&mut camera).await;
+// profile export <file>
+// profile import <file>
+// Files ending in .json are JSON, others TOML
+// profile diff <source> [<source>]
+async fn profile_command(options: &ConnectOptions, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: profile export <file> | import <file> | diff <file|address> [<file|address>]";
+    match (args.first().map(String::as_str), args.get(1)) {
+        (Some("export"), Some(file)) => {
+            let mut camera = Camera::connect(options).await?;
+            camera.read_profile().await?.save(Path::new(file))?;
+        }
+        (Some("import"), Some(file)) => {
+            let profile = Profile::load(Path::new(file))?;
+            let mut camera = Camera::connect(options).await?;
+            camera.apply_profile(&profile).await?;
+        }
+        (Some("diff"), Some(right)) => {
+            let (left, right) = match args.get(2) {
+                Some(other) => (Some(right), other),
+                None => (None, right),
+            };
+            let left = load_profile(options, left).await?;
+            let right = load_profile(options, Some(right)).await?;
+            for diff in left.diff(&right) {
+                println!("{}", diff);
+            }
+        }
+        _ => return Err(usage_error(usage)),
+    }
     Ok(())
 }
 
-async fn keepalive(camera: &mut Camera) {
-    loop {
-        let _ = camera.send_packet(Packet::with_type(SID::SUPPORT_FUNCTION_INFO, SupportFunctionInfoType::CAMERA_FUNCTION_INFO as u8)).await;
-        let _ = camera.receive_packet().await;
-        thread::sleep(Duration::from_secs(4));
-        let _ = camera.send_packet(Packet::with_type(SID::SUPPORT_FUNCTION_INFO, SupportFunctionInfoType::CAMERA_HISTORY_INFO as u8)).await;
-        let _ = camera.receive_packet().await;
-        thread::sleep(Duration::from_secs(4));
-        let _ = camera.send_packet(Packet::with_type(SID::SUPPORT_FUNCTION_INFO, SupportFunctionInfoType::CAMERA_FUNCTION_INFO as u8)).await;
-        let _ = camera.receive_packet().await;
-        thread::sleep(Duration::from_secs(4));
-    }
-}
-
-async fn support_function_version_info(camera: &mut Camera) -> Option<SupportFunctionVersionInfo> {
-    let packet = Packet::with_sid(SID::SUPPORT_FUNCTION_AND_VERSION_INFO);
-    camera.send_packet(packet).await.ok()?;
-    let response = camera.receive_packet().await?;
-    Some(SupportFunctionVersionInfo::from_bytes(&response.data))
-}
-
-async fn support_function_info(camera: &mut Camera, info_type: SupportFunctionInfoType){
-    let packet = Packet::with_type(SID::SUPPORT_FUNCTION_INFO, info_type.clone() as u8);
-    camera.send_packet(packet).await.unwrap();
-    let response = camera.receive_packet().await.unwrap();
-    match &info_type {
-        SupportFunctionInfoType::IMAGE_SUPPORT_INFO => {
-            let info = ImageSupportInfo::from_bytes(&response.data);
-            dbg!(info);
+// sound-print <base url> [<directory>]
+// The directory must be served at base url for phones to open the QR code
+async fn sound_print_command(options: &ConnectOptions, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let base_url = args.first().ok_or_else(|| usage_error("usage: sound-print <base url> [<directory>]"))?;
+    let directory = args.get(1).map_or("sound_prints", String::as_str);
+    let backend = LocalFileBackend::new(directory, base_url)?;
+    let mut camera = Camera::connect(options).await?;
+    camera.sound_print(&backend).await?;
+    Ok(())
+}
+
+// Download the recorded series and assemble it into an MJPEG AVI
+async fn write_interval_recording(camera: &mut Camera, file: &str, fps: u32) -> Result<(), Box<dyn Error>> {
+    let images = camera.interval_record_download().await?;
+    if images.is_empty() {
+        return Err("No interval recording photos available".into());
+    }
+    println!("Writing {} frames to {}", images.len(), file);
+    fs::write(file, avi::write_mjpeg_avi(&images, fps))?;
+    Ok(())
+}
+
+enum IntervalAction {
+    Start(IntervalRecordMode, u32, u16),
+    Status,
+    Stop,
+    Download(String, u32),
+    Run(IntervalRecordMode, u32, u16, String, u32),
+}
+
+fn parse_interval_action(args: &[String]) -> Option<IntervalAction> {
+    let parse_start = |args: &[String]| -> Option<(IntervalRecordMode, u32, u16)> {
+        let mode = match args.first()?.as_str() {
+            "short" => IntervalRecordMode::SHORT_INTERVAL,
+            "long" => IntervalRecordMode::LONG_INTERVAL,
+            _ => return None,
+        };
+        Some((mode, args.get(1)?.parse().ok()?, args.get(2)?.parse().ok()?))
+    };
+    let parse_fps = |arg: Option<&String>| arg.map_or(Some(5), |fps| fps.parse().ok());
+    match args.first()?.as_str() {
+        "start" => {
+            let (mode, shoot_time, shots) = parse_start(&args[1..])?;
+            Some(IntervalAction::Start(mode, shoot_time, shots))
         }
-        SupportFunctionInfoType::BATTERY_INFO => {
-            let info = BatteryInfo::from_bytes(&response.data);
-            dbg!(&info);
+        "status" => Some(IntervalAction::Status),
+        "stop" => Some(IntervalAction::Stop),
+        "download" => Some(IntervalAction::Download(args.get(1)?.clone(), parse_fps(args.get(2))?)),
+        "run" => {
+            let (mode, shoot_time, shots) = parse_start(&args[1..])?;
+            Some(IntervalAction::Run(mode, shoot_time, shots, args.get(4)?.clone(), parse_fps(args.get(5))?))
         }
-        SupportFunctionInfoType::CAMERA_FUNCTION_INFO => {
-            let info = CameraFunctionInfo::from_bytes(&response.data);
-            dbg!(&info);
+        _ => None,
+    }
+}
+
+// interval start <short|long> <shoot time> <shots>
+// interval status | stop
+// interval download <file.avi> [fps]
+// interval run <short|long> <shoot time> <shots> <file.avi> [fps]
+// Arguments are checked before connecting, so a typo does not scan and pair
+async fn interval_command(options: &ConnectOptions, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let usage = "usage: interval start <short|long> <shoot time> <shots> | status | stop | download <file.avi> [fps] | run <short|long> <shoot time> <shots> <file.avi> [fps]";
+    let action = parse_interval_action(args).ok_or_else(|| usage_error(usage))?;
+    let mut camera = Camera::connect(options).await?;
+    match action {
+        IntervalAction::Start(mode, shoot_time, shots) => {
+            camera.interval_record_start(mode, shoot_time, shots).await?;
         }
-        SupportFunctionInfoType::CAMERA_HISTORY_INFO => {
-            let info = CameraHistoryInfo::from_bytes(&response.data);
-            dbg!(&info);
+        IntervalAction::Status => {
+            let status = camera.interval_record_status().await?;
+            println!("Recording: {}", if status.is_recording { "yes" } else { "no" });
+            println!("Shots: {}/{}", status.shots_taken, status.shots_planned);
         }
-        _ => panic!("Info type: {:?} not implemented", &info_type)
-    }
-}
-
-async fn parameter_read(camera: &mut Camera, setting: ReadWriteSettingType) -> Option<ParameterReadWriteResponse> {
-    let payload = vec![setting as u8, ReadWriteSettingMode::GET_CURRENT_SETTING as u8, 0x00, 0x00, 0x00, 0x00];
-    let packet = Packet::with_data(SID::PARAMETER_RW, payload);
-    camera.send_packet(packet).await.ok()?;
-    let response = camera.receive_packet().await?;
-    Some(ParameterReadWriteResponse::from_bytes(&response.data))
-}
-
-async fn set_timedate(camera: &mut Camera) -> Option<DateTimeResponse> {
-    let now = Utc::now();
-    let formatted = now.format("%Y%m%d%H%M%S").to_string();
-    let mut bytes = formatted.into_bytes();
-    let mut payload: Vec<u8> = vec![2];
-    payload.append(&mut bytes);
-    let packet = Packet::with_data(SID::TIME_SETTING, payload);
-    camera.send_packet(packet).await.ok()?;
-    let response = camera.receive_packet().await?;
-    Some(DateTimeResponse::from_bytes(&response.data))
-}
-
-async fn automatic_photo_download(camera: &mut Camera) {
-    println!("Auto upload info");
-    let packet = Packet::with_sid(SID::IMAGE_AUTO_UPLOAD_INFO);
-    camera.send_packet(packet).await.unwrap();
-    let response = camera.receive_packet().await.unwrap();
-    if response.data[0] == 0x81 {
-        println!("No photo available");
-        return;
-    }
-    println!("Auto upload start");
-    let packet = Packet::with_data(SID::IMAGE_AUTO_UPLOAD_START, vec![0;4]);
-    camera.send_packet(packet).await.unwrap();
-    let response = camera.receive_packet().await.unwrap();
-    println!("Auto upload data");
-    let num_frames = response.data[3];
-    println!("Receiving {} frames", num_frames);
-    for frame in 0..50 {
-        let frame_num = (frame as u32).to_be_bytes().to_vec();
-        let packet = Packet::with_data(SID::IMAGE_AUTO_UPLOAD_DATA, frame_num);
-        camera.send_packet(packet).await.unwrap();
-        let _data = camera.receive_data().await.unwrap();
-        println!("Frame: {}", frame);
-        thread::sleep(Duration::from_millis(600));
-    }
-}
-
-async fn live_view_test(camera: &mut Camera) {
-    println!("Live view start");
-    let packet = Packet::with_type(SID::LIVE_VIEW_START, 0);
-    camera.send_packet(packet).await.unwrap();
-    let _response = camera.receive_packet().await.unwrap();
-    camera.send_packet(Packet::with_sid(SID::LIVE_VIEW_RECEIVE)).await.unwrap();
-    thread::sleep(Duration::from_millis(600));
-    let mut photo = Vec::<u8>::new();
-    // Receive 3 live-view photo chunks
-    for _ in 0..3 {
-        let data = camera.receive_data().await.unwrap();
-        photo.extend(data);
-    };
-    let file_path = Path::new("liveview_1.jpg");
-    fs::write(file_path, &photo[11..]).unwrap();
-    camera.send_packet(Packet::with_sid(SID::LIVE_VIEW_STOP)).await.unwrap();
+        IntervalAction::Stop => {
+            camera.interval_record_stop().await?;
+        }
+        IntervalAction::Download(file, fps) => {
+            write_interval_recording(&mut camera, &file, fps).await?;
+        }
+        IntervalAction::Run(mode, shoot_time, shots, file, fps) => {
+            camera.interval_record_start(mode, shoot_time, shots).await?;
+            loop {
+                sleep(Duration::from_secs(5)).await;
+                let status = camera.interval_record_status().await?;
+                println!("Shot {}/{}", status.shots_taken, status.shots_planned);
+                if !status.is_recording {
+                    break;
+                }
+            }
+            write_interval_recording(&mut camera, &file, fps).await?;
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use bluer::Address;
+use futures::future::join_all;
+use tracing::warn;
+
+use crate::camera::{Camera, CameraError};
+use crate::discovery::{self, ConnectOptions, DiscoveredCamera};
+use crate::profile::Profile;
+use crate::{CameraErrorType, PrinterState, SupportFunctionInfoResponse, SupportFunctionInfoType};
+
+// Connections to several cameras, addressed by alias
+// Broadcast commands run on all cameras concurrently and report one result
+// per camera, so a single failing camera does not stop the others.
+#[derive(Default)]
+pub struct CameraManager {
+    cameras: BTreeMap<String, Camera>,
+}
+
+// One row of CameraManager::status
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CameraSummary {
+    pub alias: String,
+    pub address: Address,
+    pub battery_level: Option<u8>,
+    pub is_charging: Option<bool>,
+    pub film_remaining: Option<u8>,
+    pub printer_state: Option<PrinterState>,
+    pub camera_error: Option<CameraErrorType>,
+    // Set when the camera could not be queried
+    pub error: Option<String>,
+}
+
+impl CameraSummary {
+    pub fn has_problem(&self) -> bool {
+        self.error.is_some() || self.camera_error.is_some_and(|error| error != CameraErrorType::NO_ERROR)
+    }
+}
+
+impl fmt::Display for CameraSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        write!(
+            f,
+            "{:<20} {}  battery {:>2}{}  film {:>2}  printer {:<12}",
+            self.alias,
+            self.address,
+            or_dash(self.battery_level.map(|level| level.to_string())),
+            if self.is_charging == Some(true) { "+" } else { " " },
+            or_dash(self.film_remaining.map(|film| film.to_string())),
+            or_dash(self.printer_state.map(|state| format!("{:?}", state))),
+        )?;
+        match (&self.error, self.camera_error) {
+            (Some(error), _) => write!(f, "  {}", error),
+            (None, Some(error)) if error != CameraErrorType::NO_ERROR => write!(f, "  {:?}", error),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl CameraManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Scan once and connect to every camera matching options.device
+    // Cameras are connected concurrently, those that fail are reported and skipped.
+    // Recordings replay a single camera, so options.record is refused here.
+    pub async fn connect_all(options: &ConnectOptions) -> Result<Self, CameraError> {
+        if options.record.is_some() {
+            return Err(CameraError::ConnectionFailed("session recording needs a single camera".to_string()));
+        }
+        let session = bluer::Session::new().await?;
+        let adapter = discovery::open_adapter(&session, options.adapter.as_deref()).await?;
+        let found: Vec<DiscoveredCamera> = discovery::scan(&adapter, options.scan_timeout).await?
+            .into_iter()
+            .filter(|found| options.device.matches(found))
+            .collect();
+        let connections = join_all(found.iter().map(|found| connect(&session, &adapter, found, options))).await;
+        let mut manager = Self::new();
+        for (found, connection) in found.iter().zip(connections) {
+            match connection {
+                Ok(camera) => { manager.add(&found.alias, camera); }
+                Err(err) => warn!(alias = %found.alias, error = %err, "cannot connect"),
+            }
+        }
+        if manager.is_empty() {
+            return Err(CameraError::NotFound("no Instax camera connected".to_string()));
+        }
+        Ok(manager)
+    }
+
+    // Aliases are unique, a taken alias gets the camera address appended
+    // Returns the alias the camera was added under
+    pub fn add(&mut self, alias: &str, camera: Camera) -> String {
+        let alias = if self.cameras.contains_key(alias) {
+            format!("{} ({})", alias, camera.address())
+        } else {
+            alias.to_string()
+        };
+        self.cameras.insert(alias.clone(), camera);
+        alias
+    }
+
+    // Cameras are looked up by alias, or by address when no alias matches
+    pub fn remove(&mut self, name: &str) -> Option<Camera> {
+        let alias = self.alias_of(name)?;
+        self.cameras.remove(&alias)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Camera> {
+        let alias = self.alias_of(name)?;
+        self.cameras.get_mut(&alias)
+    }
+
+    fn alias_of(&self, name: &str) -> Option<String> {
+        if self.cameras.contains_key(name) {
+            return Some(name.to_string());
+        }
+        let address: Address = name.parse().ok()?;
+        self.cameras.iter().find(|(_, camera)| camera.address() == address).map(|(alias, _)| alias.clone())
+    }
+
+    pub fn aliases(&self) -> impl Iterator<Item = &String> {
+        self.cameras.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cameras.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cameras.is_empty()
+    }
+
+    pub async fn set_timedate_all(&mut self) -> Vec<(String, Result<(), CameraError>)> {
+        join_all(self.cameras.iter_mut().map(|(alias, camera)| async move {
+            (alias.clone(), camera.set_timedate().await.map(|_| ()))
+        })).await
+    }
+
+    pub async fn apply_profile_all(&mut self, profile: &Profile) -> Vec<(String, Result<(), CameraError>)> {
+        join_all(self.cameras.iter_mut().map(|(alias, camera)| async move {
+            (alias.clone(), camera.apply_profile(profile).await)
+        })).await
+    }
+
+    // Battery, film and error state of every camera, in alias order
+    pub async fn status(&mut self) -> Vec<CameraSummary> {
+        join_all(self.cameras.iter_mut().map(|(alias, camera)| summarize(alias, camera))).await
+    }
+}
+
+async fn connect(session: &bluer::Session, adapter: &bluer::Adapter, found: &DiscoveredCamera, options: &ConnectOptions) -> Result<Camera, CameraError> {
+    let device = adapter.device(found.address)?;
+    if options.pair {
+        discovery::pair(session, &device).await?;
+    }
+    Camera::from_device(device, None).await
+}
+
+async fn summarize(alias: &str, camera: &mut Camera) -> CameraSummary {
+    let mut summary = CameraSummary {
+        alias: alias.to_string(),
+        address: camera.address(),
+        battery_level: None,
+        is_charging: None,
+        film_remaining: None,
+        printer_state: None,
+        camera_error: None,
+        error: None,
+    };
+    let info_types = [SupportFunctionInfoType::CAMERA_FUNCTION_INFO, SupportFunctionInfoType::PRINTER_FUNCTION_INFO];
+    for info_type in info_types {
+        match camera.support_function_info(info_type).await {
+            Ok(SupportFunctionInfoResponse::CameraFunctionInfo(info)) => {
+                summary.battery_level = Some(info.battery_level);
+                summary.is_charging = Some(info.is_charging);
+                summary.camera_error = Some(info.camera_error_type);
+            }
+            Ok(SupportFunctionInfoResponse::PrinterFunctionInfo(info)) => {
+                summary.film_remaining = Some(info.film_remaining);
+                summary.printer_state = Some(info.printer_state);
+            }
+            Ok(_) | Err(CameraError::Unsupported(_)) => {}
+            Err(err) => {
+                summary.error = Some(err.to_string());
+                break;
+            }
+        }
+    }
+    summary
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::ReadWriteSettingType;
+
+// Camera settings profile
+// Stored as a flat TOML table keyed by setting name:
+//
+// [settings]
+// FLASH_SETTING = 1
+// VOLUME_SETTING = 3
+//
+// or, with the serde feature, as the same table in JSON:
+//
+// {"settings": {"FLASH_SETTING": 1, "VOLUME_SETTING": 3}}
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Profile {
+    pub settings: BTreeMap<ReadWriteSettingType, u32>,
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Syntax(usize, String),
+    UnknownSetting(usize, String),
+    InvalidValue(usize, String),
+    Json(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProfileError::Syntax(line, text) => write!(f, "line {}: cannot parse \"{}\"", line, text),
+            ProfileError::UnknownSetting(line, name) => write!(f, "line {}: unknown setting {}", line, name),
+            ProfileError::InvalidValue(line, value) => write!(f, "line {}: invalid value {}", line, value),
+            ProfileError::Json(err) => write!(f, "invalid JSON profile: {}", err),
+            ProfileError::Io(err) => write!(f, "cannot access profile: {}", err),
+        }
+    }
+}
+
+impl Error for ProfileError {}
+
+impl From<io::Error> for ProfileError {
+    fn from(err: io::Error) -> Self {
+        ProfileError::Io(err)
+    }
+}
+
+// One setting that differs between two profiles
+// None means the setting is missing on that side
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SettingDiff {
+    pub setting: ReadWriteSettingType,
+    pub left: Option<u32>,
+    pub right: Option<u32>,
+}
+
+impl fmt::Display for SettingDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let show = |value: Option<u32>| value.map_or("-".to_string(), |v| v.to_string());
+        write!(f, "{}: {} -> {}", self.setting.name(), show(self.left), show(self.right))
+    }
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut out = String::from("[settings]\n");
+        for (setting, value) in &self.settings {
+            out.push_str(&format!("{} = {}\n", setting.name(), value));
+        }
+        out
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, ProfileError> {
+        let mut profile = Profile::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_num = index + 1;
+            let line = match line.split_once('#') {
+                Some((content, _comment)) => content,
+                None => line,
+            }.trim();
+            if line.is_empty() || line == "[settings]" {
+                continue;
+            }
+            let (name, value) = line.split_once('=')
+                .ok_or_else(|| ProfileError::Syntax(line_num, line.to_string()))?;
+            let (name, value) = (name.trim(), value.trim());
+            let setting = ReadWriteSettingType::from_name(name)
+                .ok_or_else(|| ProfileError::UnknownSetting(line_num, name.to_string()))?;
+            let value = value.parse::<u32>()
+                .map_err(|_| ProfileError::InvalidValue(line_num, value.to_string()))?;
+            profile.settings.insert(setting, value);
+        }
+        Ok(profile)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("profiles always serialize")
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(text: &str) -> Result<Self, ProfileError> {
+        serde_json::from_str(text).map_err(|err| ProfileError::Json(err.to_string()))
+    }
+
+    // Files ending in .json are JSON, anything else TOML
+    pub fn load(path: &Path) -> Result<Self, ProfileError> {
+        let text = fs::read_to_string(path)?;
+        match is_json(path) {
+            #[cfg(feature = "serde")]
+            true => Self::from_json(&text),
+            #[cfg(not(feature = "serde"))]
+            true => Err(ProfileError::Json("JSON profiles need the serde feature".to_string())),
+            false => Self::from_toml(&text),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ProfileError> {
+        let text = match is_json(path) {
+            #[cfg(feature = "serde")]
+            true => self.to_json(),
+            #[cfg(not(feature = "serde"))]
+            true => return Err(ProfileError::Json("JSON profiles need the serde feature".to_string())),
+            false => self.to_toml(),
+        };
+        Ok(fs::write(path, text)?)
+    }
+
+    // Settings whose values differ, in setting order
+    pub fn diff(&self, other: &Profile) -> Vec<SettingDiff> {
+        ReadWriteSettingType::ALL.into_iter()
+            .map(|setting| SettingDiff {
+                setting,
+                left: self.settings.get(&setting).copied(),
+                right: other.settings.get(&setting).copied(),
+            })
+            .filter(|diff| diff.left != diff.right)
+            .collect()
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
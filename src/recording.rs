@@ -0,0 +1,201 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use futures::future::BoxFuture;
+use tracing::warn;
+
+use crate::camera::CameraError;
+use crate::packet;
+use crate::transport::Transport;
+
+// Session recordings are text files with one frame per line:
+//   <seconds since start> <SENT|RECV> <hex bytes>
+// Lines starting with # are comments.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameDirection {
+    Sent,
+    Received,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub time: Duration,
+    pub direction: FrameDirection,
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    pub fn sent(data: Vec<u8>) -> Self {
+        Frame { time: Duration::ZERO, direction: FrameDirection::Sent, data }
+    }
+
+    pub fn received(data: Vec<u8>) -> Self {
+        Frame { time: Duration::ZERO, direction: FrameDirection::Received, data }
+    }
+
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        let time = Duration::try_from_secs_f64(fields.next()?.parse().ok()?).ok()?;
+        let direction = match fields.next()? {
+            "SENT" => FrameDirection::Sent,
+            "RECV" => FrameDirection::Received,
+            _ => return None,
+        };
+        let data = packet::parse_hex(fields.next()?)?;
+        if data.is_empty() || fields.next().is_some() {
+            return None;
+        }
+        Some(Frame { time, direction, data })
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let direction = match self.direction {
+            FrameDirection::Sent => "SENT",
+            FrameDirection::Received => "RECV",
+        };
+        write!(f, "{:.6} {} {}", self.time.as_secs_f64(), direction, packet::hex(&self.data))
+    }
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(io::Error),
+    InvalidLine(usize),
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordingError::Io(err) => write!(f, "cannot read recording: {}", err),
+            RecordingError::InvalidLine(line) => write!(f, "invalid frame on line {}", line),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+impl From<io::Error> for RecordingError {
+    fn from(err: io::Error) -> Self {
+        RecordingError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recording {
+    pub frames: Vec<Frame>,
+}
+
+impl Recording {
+    pub fn from_text(text: &str) -> Result<Self, RecordingError> {
+        let mut frames = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            frames.push(Frame::from_line(line).ok_or(RecordingError::InvalidLine(number + 1))?);
+        }
+        Ok(Recording { frames })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, RecordingError> {
+        Self::from_text(&fs::read_to_string(path)?)
+    }
+
+    pub fn to_text(&self) -> String {
+        self.frames.iter().map(|frame| format!("{}\n", frame)).collect()
+    }
+}
+
+// Appends every frame passing through a Camera to a recording file
+// Clones share the file and start time, so a reconnected link keeps writing
+// to the same recording.
+#[derive(Debug, Clone)]
+pub struct SessionRecorder {
+    writer: Arc<Mutex<BufWriter<File>>>,
+    start: Instant,
+}
+
+impl SessionRecorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "# instax_pal session recorded {}", chrono::Local::now().to_rfc3339())?;
+        writer.flush()?;
+        Ok(SessionRecorder { writer: Arc::new(Mutex::new(writer)), start: Instant::now() })
+    }
+
+    pub fn record(&self, direction: FrameDirection, data: &[u8]) {
+        let frame = Frame { time: self.start.elapsed(), direction, data: data.to_vec() };
+        let mut writer = self.writer.lock().unwrap();
+        // Flushed per frame so a crash still leaves a usable recording
+        if let Err(err) = writeln!(writer, "{}", frame).and_then(|_| writer.flush()) {
+            warn!(error = %err, "cannot write session recording");
+        }
+    }
+}
+
+// Plays back a recording in place of a camera, ignoring its timing
+// Each sent frame must match the next SENT frame of the recording; by default
+// only the SID is compared since payloads such as TIME_SETTING carry the
+// current time. Received frames are handed out up to the next SENT frame.
+pub struct ReplayTransport {
+    frames: VecDeque<Frame>,
+    strict: bool,
+}
+
+impl ReplayTransport {
+    pub fn new(recording: Recording) -> Self {
+        ReplayTransport { frames: recording.frames.into(), strict: false }
+    }
+
+    // Compare sent frames byte for byte
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    // Frames not played back yet
+    pub fn remaining(&self) -> usize {
+        self.frames.len()
+    }
+
+    fn check_sent(&mut self, data: &[u8]) -> Result<(), CameraError> {
+        // Replies the client never waited for are skipped, as a camera would
+        while self.frames.front().is_some_and(|frame| frame.direction == FrameDirection::Received) {
+            self.frames.pop_front();
+        }
+        let expected = self.frames.pop_front()
+            .ok_or_else(|| CameraError::Replay(format!("sent {} after the end of the recording", packet::describe(data))))?;
+        let matches = if self.strict {
+            expected.data == data
+        } else {
+            packet::read_u16(&expected.data, 4) == packet::read_u16(data, 4)
+        };
+        if !matches {
+            return Err(CameraError::Replay(format!("sent {}, recording has {}", packet::describe(data), packet::describe(&expected.data))));
+        }
+        Ok(())
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&mut self, data: Vec<u8>) -> BoxFuture<'_, Result<(), CameraError>> {
+        let result = self.check_sent(&data);
+        Box::pin(async move { result })
+    }
+
+    fn receive(&mut self) -> BoxFuture<'_, Option<Vec<u8>>> {
+        let frame = match self.frames.front() {
+            Some(frame) if frame.direction == FrameDirection::Received => self.frames.pop_front(),
+            _ => None,
+        };
+        Box::pin(async move { frame.map(|frame| frame.data) })
+    }
+}
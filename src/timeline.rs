@@ -0,0 +1,259 @@
+use std::fmt;
+use std::time::Duration;
+use num_traits::FromPrimitive;
+
+use crate::codec::Layout;
+use crate::packet;
+use crate::recording::{Frame, FrameDirection, Recording};
+use crate::*;
+
+// Annotated view of a recorded or imported session
+// Each frame is labelled with packet::describe and decoded with the payload
+// layouts Camera encodes and decodes with. Anything the crate does not know yet, such as unknown
+// SIDs, enum values or trailing payload bytes, is listed as a warning.
+
+#[derive(Debug, Clone)]
+pub struct TimelineEntry {
+    pub time: Duration,
+    pub direction: FrameDirection,
+    // packet::describe output
+    pub frame: String,
+    pub decoded: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+impl fmt::Display for TimelineEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let arrow = match self.direction {
+            FrameDirection::Sent => "->",
+            FrameDirection::Received => "<-",
+        };
+        write!(f, "{:>10.6} {} {}", self.time.as_secs_f64(), arrow, self.frame)?;
+        if let Some(decoded) = &self.decoded {
+            write!(f, "\n{:>13} {}", "=", decoded)?;
+        }
+        for warning in &self.warnings {
+            write!(f, "\n{:>13} {}", "!", warning)?;
+        }
+        Ok(())
+    }
+}
+
+// SIDs transferring data in numbered chunks, index(4) + data
+const CHUNK_SIDS: [SID; 12] = [
+    SID::PRINT_IMAGE_DOWNLOAD_DATA,
+    SID::FW_DOWNLOAD_DATA,
+    SID::URL_PICTURE_UPLOAD,
+    SID::URL_AUDIO_UPLOAD,
+    SID::POST_VIEW_UPLOAD,
+    SID::FRAME_PICTURE_DOWNLOAD,
+    SID::SOUND_DOWNLOAD,
+    SID::IMAGE_AUTO_UPLOAD_DATA,
+    SID::CAMERA_LOG_SUBTOTAL_DATA,
+    SID::CAMERA_LOG_DATE_DATA,
+    SID::CAMERA_LOG_FILTER_DATA,
+    SID::CAMERA_LOG_RECORD_DATE_DATA,
+];
+
+pub fn annotate(recording: &Recording) -> Vec<TimelineEntry> {
+    let mut annotator = Annotator::new();
+    recording.frames.iter().map(|frame| annotator.entry(frame)).collect()
+}
+
+// Annotates frames one at a time, keeping what earlier frames revealed
+#[derive(Default)]
+pub struct Annotator {
+    // From the last SUPPORT_FUNCTION_AND_VERSION_INFO reply, needed to decode
+    // SUPPORT_FUNCTION_INFO replies
+    versions: Option<SupportFunctionVersionInfo>,
+}
+
+impl Annotator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // For sessions joined after the version exchange, e.g. from Camera::capabilities
+    pub fn with_versions(versions: SupportFunctionVersionInfo) -> Self {
+        Annotator { versions: Some(versions) }
+    }
+
+    pub fn entry(&mut self, frame: &Frame) -> TimelineEntry {
+        let mut entry = TimelineEntry {
+            time: frame.time,
+            direction: frame.direction,
+            frame: packet::describe(&frame.data),
+            decoded: None,
+            warnings: Vec::new(),
+        };
+        self.annotate(&mut entry, &frame.data);
+        entry
+    }
+
+    fn annotate(&mut self, entry: &mut TimelineEntry, data: &[u8]) {
+        let size = packet::read_u16(data, 2);
+        if data.len() < 7 || size != Some(data.len() as u16) {
+            entry.warnings.push("malformed frame".to_string());
+            return;
+        }
+        if data.iter().fold(0u8, |a, &b| a.wrapping_add(b)) != 255 {
+            entry.warnings.push("checksum mismatch".to_string());
+        }
+        let code = packet::read_u16(data, 4).unwrap();
+        let sid = match SID::from_u16(code) {
+            Some(sid) if sid != SID::UNKNOWN => sid,
+            _ => {
+                entry.warnings.push(format!("unknown SID 0x{:04x}", code));
+                return;
+            }
+        };
+        let payload = &data[6..data.len() - 1];
+        match entry.direction {
+            FrameDirection::Sent => self.request(entry, sid, payload),
+            FrameDirection::Received => self.response(entry, sid, payload),
+        }
+    }
+
+    fn request(&mut self, entry: &mut TimelineEntry, sid: SID, payload: &[u8]) {
+        if payload.is_empty() {
+            return;
+        }
+        let decoded = match sid {
+            _ if CHUNK_SIDS.contains(&sid) => Some(chunk(entry, payload)),
+            SID::SUPPORT_FUNCTION_INFO => describe::<SupportFunctionInfoRequest>(entry, payload, true),
+            SID::DEVICE_INFO_SERVICE => describe::<DeviceInfoRequest>(entry, payload, true),
+            SID::EXECUTE_CAMERA_COMMAND => describe::<CameraCommandRequest>(entry, payload, true),
+            SID::TIME_SETTING => describe::<DateTimeRequest>(entry, payload, false),
+            SID::AUTO_SLEEP_SETTINGS => describe::<AutoSleepRequest>(entry, payload, true),
+            SID::PARAMETER_RW => describe::<ParameterReadWriteRequest>(entry, payload, true),
+            SID::CAMERA_SETTINGS => describe::<CameraSettingsRequest>(entry, payload, true),
+            SID::INTERVAL_RECORD_START => describe::<IntervalRecordStartRequest>(entry, payload, true),
+            SID::URL_UPLOAD_ADDRESS => describe::<UrlUploadAddress>(entry, payload, false),
+            _ => {
+                entry.warnings.push(format!("undecoded payload {}", packet::hex(payload)));
+                None
+            }
+        };
+        entry.decoded = decoded;
+    }
+
+    fn response(&mut self, entry: &mut TimelineEntry, sid: SID, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        let decoded = match sid {
+            _ if CHUNK_SIDS.contains(&sid) => Some(chunk(entry, data)),
+            SID::SUPPORT_FUNCTION_AND_VERSION_INFO => layout::<SupportFunctionVersionInfo>(entry, data, true).map(|versions| {
+                let decoded = format!("{:?}", versions);
+                self.versions = Some(versions);
+                decoded
+            }),
+            SID::SUPPORT_FUNCTION_INFO => failure(entry, data).or_else(|| self.support_function_info(entry, data)),
+            SID::DEVICE_INFO_SERVICE => failure(entry, data).or_else(|| {
+                layout::<DeviceInfoEntry>(entry, data, false)
+                    .map(|info| format!("{:?} = {:?}", info.info_type, String::from_utf8_lossy(&info.value)))
+            }),
+            SID::TIME_SETTING => failure(entry, data).or_else(|| describe::<DateTimeResponse>(entry, data, false)),
+            SID::EXECUTE_CAMERA_COMMAND
+            | SID::CAMERA_SETTINGS
+            | SID::AUTO_SLEEP_SETTINGS
+            | SID::IMAGE_AUTO_UPLOAD_INFO
+            | SID::URL_UPLOAD_ADDRESS
+            | SID::URL_UPLOAD_DATA_COMPLETE
+            | SID::INTERVAL_RECORD_START
+            | SID::INTERVAL_RECORD_STOP
+            | SID::IMAGE_AUTO_UPLOAD_END
+            | SID::IMAGE_AUTO_UPLOAD_COMPLETE
+            | SID::URL_PICTURE_UPLOAD_START
+            | SID::URL_PICTURE_UPLOAD_END
+            | SID::URL_AUDIO_UPLOAD_START
+            | SID::URL_AUDIO_UPLOAD_END
+            | SID::SHUT_DOWN
+            | SID::RESET => failure(entry, data).or_else(|| describe::<StatusReply>(entry, data, true)),
+            SID::PARAMETER_RW => failure(entry, data).or_else(|| describe::<ParameterReadWriteResponse>(entry, data, true)),
+            SID::CHECK_CAMERA_STATUS => failure(entry, data).or_else(|| describe::<CameraStatus>(entry, data, true)),
+            SID::URL_UPLOAD_INFO => failure(entry, data).or_else(|| describe::<UrlUploadInfo>(entry, data, true)),
+            SID::IMAGE_AUTO_UPLOAD_START => failure(entry, data).or_else(|| describe::<AutoUploadStartResponse>(entry, data, true)),
+            SID::INTERVAL_RECORD_STATUS => failure(entry, data).or_else(|| describe::<IntervalRecordStatus>(entry, data, true)),
+            SID::CAMERA_LOG_SUBTOTAL_START
+            | SID::CAMERA_LOG_DATE_START
+            | SID::CAMERA_LOG_FILTER_START
+            | SID::CAMERA_LOG_RECORD_DATE_START => failure(entry, data).or_else(|| describe::<CameraLogStartResponse>(entry, data, true)),
+            _ => {
+                entry.warnings.push(format!("undecoded payload {}", packet::hex(data)));
+                None
+            }
+        };
+        entry.decoded = decoded;
+    }
+
+    fn support_function_info(&mut self, entry: &mut TimelineEntry, data: &[u8]) -> Option<String> {
+        let versions = self.versions.clone().unwrap_or_else(|| {
+            entry.warnings.push("version info not captured, assuming layout version 1".to_string());
+            SupportFunctionVersionInfo {
+                support_function_info: 1,
+                device_info_version: 1,
+                support_img_info_version: 1,
+                battery_info_version: 1,
+                printer_func_info_version: 1,
+                printer_history_info_version: 1,
+                camera_func_info_version: 1,
+                camera_history_info_version: 1,
+            }
+        });
+        match SupportFunctionInfoResponse::decode(data, &versions) {
+            Ok(info) => Some(format!("{:?}", info)),
+            Err(err) => {
+                entry.warnings.push(err.to_string());
+                None
+            }
+        }
+    }
+}
+
+// Status of a reply leading with one, warning about codes no capture shows
+// Returns the status of a failed request, None when the data after an OK
+// status is still to be decoded
+fn failure(entry: &mut TimelineEntry, data: &[u8]) -> Option<String> {
+    let status = layout::<StatusReply>(entry, data, false)?.status;
+    if !status.is_verified() {
+        entry.warnings.push(format!("unverified status 0x{:02x}", status.code()));
+    }
+    (status != ResponseStatus::OK).then(|| format!("{:?}", status))
+}
+
+fn chunk(entry: &mut TimelineEntry, payload: &[u8]) -> String {
+    match payload.get(0..4) {
+        Some(index) => format!("chunk {} with {} bytes", u32::from_be_bytes(index.try_into().unwrap()), payload.len() - 4),
+        None => {
+            entry.warnings.push(format!("chunk without index {}", packet::hex(payload)));
+            format!("{} bytes", payload.len())
+        }
+    }
+}
+
+// Decodes a payload layout, warning about decode errors and, for fixed size
+// layouts, extra bytes
+fn layout<T: Layout>(entry: &mut TimelineEntry, data: &[u8], fixed: bool) -> Option<T> {
+    match T::decode(data) {
+        Ok(value) => {
+            if fixed && data.len() > T::min_len() {
+                entry.warnings.push(format!("unknown trailing bytes {}", packet::hex(&data[T::min_len()..])));
+            }
+            Some(value)
+        }
+        // An unknown enum value names itself, a short payload is shown
+        Err(err @ DecodeError::TooShort { .. }) => {
+            entry.warnings.push(format!("{}: {}", err, packet::hex(data)));
+            None
+        }
+        Err(err) => {
+            entry.warnings.push(err.to_string());
+            None
+        }
+    }
+}
+
+fn describe<T: Layout + fmt::Debug>(entry: &mut TimelineEntry, data: &[u8], fixed: bool) -> Option<String> {
+    layout::<T>(entry, data, fixed).map(|value| format!("{:?}", value))
+}
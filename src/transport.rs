@@ -0,0 +1,41 @@
+use std::pin::Pin;
+use bluer::gatt::remote::Characteristic;
+use futures::future::BoxFuture;
+use futures::{Stream, StreamExt};
+
+use crate::camera::CameraError;
+
+// Moves raw frames between Camera and a device
+// BleTransport talks to a real camera, recording::ReplayTransport plays back a
+// recorded session.
+pub trait Transport: Send {
+    fn send(&mut self, data: Vec<u8>) -> BoxFuture<'_, Result<(), CameraError>>;
+    // None once the device is gone
+    fn receive(&mut self) -> BoxFuture<'_, Option<Vec<u8>>>;
+}
+
+// Commands are written to INSTAX_WRITE_UUID, responses arrive as
+// INSTAX_NOTIFY_UUID notifications
+pub struct BleTransport {
+    write_characteristic: Characteristic,
+    notify_stream: Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>,
+}
+
+impl BleTransport {
+    pub fn new(write_characteristic: Characteristic, notify_stream: Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>) -> Self {
+        BleTransport { write_characteristic, notify_stream }
+    }
+}
+
+impl Transport for BleTransport {
+    fn send(&mut self, data: Vec<u8>) -> BoxFuture<'_, Result<(), CameraError>> {
+        Box::pin(async move {
+            self.write_characteristic.write(&data).await?;
+            Ok(())
+        })
+    }
+
+    fn receive(&mut self) -> BoxFuture<'_, Option<Vec<u8>>> {
+        Box::pin(self.notify_stream.next())
+    }
+}
@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::prelude::*;
+
+// Publishes the picture and audio of a sound print
+// Returns the URL the camera embeds in the printed QR code
+pub trait UploadBackend {
+    fn upload(&self, picture: &[u8], audio: &[u8]) -> Result<String, Box<dyn Error>>;
+}
+
+#[derive(Debug)]
+pub enum UploadError {
+    InvalidBaseUrl(String),
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UploadError::InvalidBaseUrl(url) => write!(f, "base URL {} is not an http(s) URL", url),
+        }
+    }
+}
+
+impl Error for UploadError {}
+
+// Default backend: stores uploads in a local directory
+// Each upload gets a small HTML page playing the audio next to the picture.
+// The directory has to be served by a static file server reachable from the
+// phone scanning the QR code, so base_url must be that server's http(s) URL
+pub struct LocalFileBackend {
+    pub directory: PathBuf,
+    pub base_url: String,
+}
+
+impl LocalFileBackend {
+    pub fn new(directory: impl Into<PathBuf>, base_url: &str) -> Result<Self, UploadError> {
+        if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+            return Err(UploadError::InvalidBaseUrl(base_url.to_string()));
+        }
+        Ok(LocalFileBackend {
+            directory: directory.into(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+impl UploadBackend for LocalFileBackend {
+    fn upload(&self, picture: &[u8], audio: &[u8]) -> Result<String, Box<dyn Error>> {
+        fs::create_dir_all(&self.directory)?;
+        let id = Utc::now().format("%Y%m%d%H%M%S%3f").to_string();
+        fs::write(self.directory.join(format!("{}.jpg", id)), picture)?;
+        fs::write(self.directory.join(format!("{}.audio", id)), audio)?;
+        let page = format!(
+            "<!DOCTYPE html>\n<html>\n<body>\n<img src=\"{id}.jpg\">\n<audio controls src=\"{id}.audio\"></audio>\n</body>\n</html>\n",
+            id = id
+        );
+        fs::write(self.directory.join(format!("{}.html", id)), page)?;
+        Ok(format!("{}/{}.html", self.base_url, id))
+    }
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+use instax_pal::advertisement::*;
+use instax_pal::camera::INSTAX_SERVICE_UUID;
+
+// Synthetic payloads following the guessed layout in advertisement.rs
+#[test]
+fn decodes_manufacturer_data() {
+    let manufacturer_data = HashMap::from([(FUJIFILM_COMPANY_ID, vec![0x03, 0x03, 0x14])]);
+    let advertisement = Advertisement::parse(&manufacturer_data, &HashMap::new());
+    assert_eq!(advertisement.model_id, Some(0x03));
+    assert_eq!(advertisement.is_awake, Some(true));
+    assert_eq!(advertisement.is_charging, Some(true));
+    assert_eq!(advertisement.battery_level, Some(4));
+    assert_eq!(advertisement.service_data, None);
+}
+
+#[test]
+fn falls_back_to_service_data() {
+    let service_data = HashMap::from([(INSTAX_SERVICE_UUID, vec![0x05, 0x00, 0x02])]);
+    let advertisement = Advertisement::parse(&HashMap::new(), &service_data);
+    assert_eq!(advertisement.model_id, Some(0x05));
+    assert_eq!(advertisement.is_awake, Some(false));
+    assert_eq!(advertisement.battery_level, Some(2));
+}
+
+#[test]
+fn ignores_other_companies_and_short_payloads() {
+    let manufacturer_data = HashMap::from([(0x004c, vec![0x01, 0x01, 0x01])]);
+    assert!(Advertisement::parse(&manufacturer_data, &HashMap::new()).is_empty());
+
+    let manufacturer_data = HashMap::from([(FUJIFILM_COMPANY_ID, vec![0x03])]);
+    let advertisement = Advertisement::parse(&manufacturer_data, &HashMap::new());
+    assert_eq!(advertisement.model_id, Some(0x03));
+    assert_eq!(advertisement.is_awake, None);
+    assert_eq!(advertisement.battery_level, None);
+}
@@ -0,0 +1,50 @@
+use instax_pal::avi::{jpeg_dimensions, write_mjpeg_avi};
+
+// SOI, an APP0 segment, then SOF0 with the given size
+fn jpeg(width: u16, height: u16, body: &[u8]) -> Vec<u8> {
+    let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xC0, 0x00, 0x0B, 0x08];
+    jpeg.extend(height.to_be_bytes());
+    jpeg.extend(width.to_be_bytes());
+    jpeg.extend([0x01, 0x01, 0x11, 0x00]);
+    jpeg.extend(body);
+    jpeg.extend([0xFF, 0xD9]);
+    jpeg
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+#[test]
+fn finds_jpeg_dimensions() {
+    assert_eq!(jpeg_dimensions(&jpeg(640, 480, &[])), Some((640, 480)));
+    assert_eq!(jpeg_dimensions(&[0x89, b'P', b'N', b'G']), None);
+    // Cut off inside the SOF segment
+    assert_eq!(jpeg_dimensions(&jpeg(640, 480, &[])[..17]), None);
+}
+
+#[test]
+fn sizes_riff_and_index_chunks() {
+    let frames = vec![jpeg(320, 240, &[0xAA]), jpeg(320, 240, &[0xBB, 0xCC])];
+    let avi = write_mjpeg_avi(&frames, 2);
+
+    assert_eq!(&avi[0..4], b"RIFF");
+    assert_eq!(u32_at(&avi, 4) as usize, avi.len() - 8);
+    assert_eq!(&avi[8..12], b"AVI ");
+    let idx1 = avi.windows(4).rposition(|fourcc| fourcc == b"idx1").unwrap();
+    assert_eq!(u32_at(&avi, idx1 + 4), 2 * 16);
+    assert_eq!(idx1 + 8 + 32, avi.len());
+    // Second entry: offset past the first, odd-sized and padded, 00dc chunk
+    let movi = avi.windows(4).position(|fourcc| fourcc == b"movi").unwrap();
+    let second = idx1 + 8 + 16;
+    let offset = u32_at(&avi, second + 8) as usize;
+    assert_eq!(&avi[movi + offset..movi + offset + 4], b"00dc");
+    assert_eq!(u32_at(&avi, second + 12) as usize, frames[1].len());
+    assert_eq!(offset, 4 + 8 + frames[0].len() + frames[0].len() % 2);
+}
+
+#[test]
+fn large_frames_do_not_overflow_header_fields() {
+    let avi = write_mjpeg_avi(&[jpeg(u16::MAX, u16::MAX, &[])], u32::MAX);
+    assert_eq!(u32_at(&avi, 4) as usize, avi.len() - 8);
+}
@@ -0,0 +1,47 @@
+use chrono::prelude::*;
+use instax_pal::battery::*;
+use instax_pal::ChargerStateType;
+
+// Synthetic samples, as BatteryMonitor sees them after CameraFunctionInfo decoding;
+// a full charge is reported as CHARGE_COMPLETE with the charging flag cleared
+fn sample(battery_level: u8, is_charging: bool, charger_state: ChargerStateType) -> BatterySample {
+    BatterySample { time: Local::now(), battery_level, is_charging, charger_state }
+}
+
+#[test]
+fn reports_threshold_crossings() {
+    let mut monitor = BatteryMonitor::new(vec![2, 4]);
+    assert_eq!(monitor.update(sample(5, false, ChargerStateType::NOT_CHARGING)), vec![]);
+    assert_eq!(monitor.update(sample(4, false, ChargerStateType::NOT_CHARGING)), vec![]);
+    assert_eq!(monitor.update(sample(3, false, ChargerStateType::NOT_CHARGING)), vec![BatteryEvent::Low(4)]);
+    assert_eq!(monitor.update(sample(1, false, ChargerStateType::NOT_CHARGING)), vec![BatteryEvent::Low(2)]);
+    assert_eq!(monitor.update(sample(1, false, ChargerStateType::NOT_CHARGING)), vec![]);
+    assert_eq!(
+        monitor.update(sample(4, false, ChargerStateType::NOT_CHARGING)),
+        vec![BatteryEvent::Recovered(2), BatteryEvent::Recovered(4)]
+    );
+}
+
+#[test]
+fn first_sample_reports_low_battery() {
+    let mut monitor = BatteryMonitor::new(vec![2, 4]);
+    assert_eq!(monitor.update(sample(3, true, ChargerStateType::CHARGING)), vec![BatteryEvent::Low(4)]);
+}
+
+#[test]
+fn reports_charging_changes() {
+    let mut monitor = BatteryMonitor::new(vec![]);
+    monitor.update(sample(2, false, ChargerStateType::NOT_CHARGING));
+    assert_eq!(
+        monitor.update(sample(2, true, ChargerStateType::CHARGING)),
+        vec![BatteryEvent::ChargingStarted, BatteryEvent::ChargerStateChanged(ChargerStateType::CHARGING)]
+    );
+    assert_eq!(
+        monitor.update(sample(4, false, ChargerStateType::CHARGE_COMPLETE)),
+        vec![BatteryEvent::ChargingStopped, BatteryEvent::ChargerStateChanged(ChargerStateType::CHARGE_COMPLETE)]
+    );
+    assert_eq!(
+        monitor.update(sample(4, false, ChargerStateType::NOT_CHARGING)),
+        vec![BatteryEvent::ChargerStateChanged(ChargerStateType::NOT_CHARGING)]
+    );
+}
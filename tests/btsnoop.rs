@@ -0,0 +1,128 @@
+use instax_pal::btsnoop;
+use instax_pal::packet::Packet;
+use instax_pal::camera::{INSTAX_NOTIFY_UUID, INSTAX_WRITE_UUID};
+use instax_pal::recording::{Frame, FrameDirection, Recording};
+use instax_pal::timeline;
+use instax_pal::SID;
+
+// Frame for a SID the crate does not know
+fn unknown_frame(sid: u16, payload: &[u8]) -> Vec<u8> {
+    let mut frame = b"Ab".to_vec();
+    frame.extend((7 + payload.len() as u16).to_be_bytes());
+    frame.extend(sid.to_be_bytes());
+    frame.extend(payload);
+    frame.push(255 - frame.iter().fold(0u8, |a, &b| a.wrapping_add(b)));
+    frame
+}
+
+fn log(records: &[(bool, Vec<u8>)]) -> Vec<u8> {
+    let mut log = b"btsnoop\0".to_vec();
+    log.extend(1u32.to_be_bytes());
+    log.extend(1002u32.to_be_bytes());
+    for (i, (received, data)) in records.iter().enumerate() {
+        log.extend((data.len() as u32).to_be_bytes());
+        log.extend((data.len() as u32).to_be_bytes());
+        log.extend((*received as u32).to_be_bytes());
+        log.extend(0u32.to_be_bytes());
+        log.extend((0x00e0_0000_0000_0000u64 + i as u64 * 1000).to_be_bytes());
+        log.extend(data);
+    }
+    log
+}
+
+// H4 ACL packet on connection 0x0040, boundary flag 0b10 starts a frame, 0b01 continues it
+fn acl(boundary: u8, fragment: &[u8]) -> Vec<u8> {
+    let mut acl = vec![0x02, 0x40, boundary << 4];
+    acl.extend((fragment.len() as u16).to_le_bytes());
+    acl.extend(fragment);
+    acl
+}
+
+fn l2cap(pdu: &[u8]) -> Vec<u8> {
+    let mut l2cap = (pdu.len() as u16).to_le_bytes().to_vec();
+    l2cap.extend(4u16.to_le_bytes());
+    l2cap.extend(pdu);
+    l2cap
+}
+
+fn att(opcode: u8, handle: u16, value: &[u8]) -> Vec<u8> {
+    let mut pdu = vec![opcode];
+    pdu.extend(handle.to_le_bytes());
+    pdu.extend(value);
+    acl(0b10, &l2cap(&pdu))
+}
+
+fn declaration(value_handle: u16, uuid: u128) -> Vec<u8> {
+    let mut entry = (value_handle - 1).to_le_bytes().to_vec();
+    entry.push(0x1a);
+    entry.extend(value_handle.to_le_bytes());
+    entry.extend(uuid.to_le_bytes());
+    entry
+}
+
+#[test]
+fn imports_frames_on_discovered_characteristics() {
+    let mut discovery = vec![0x09, 21];
+    discovery.extend(declaration(0x0010, INSTAX_WRITE_UUID.as_u128()));
+    discovery.extend(declaration(0x0012, INSTAX_NOTIFY_UUID.as_u128()));
+    discovery.extend(declaration(0x0020, 0x1234));
+    let request = Packet::with_data(SID::SUPPORT_FUNCTION_INFO, vec![0x04]).pack();
+    let response = Packet::response(SID::SUPPORT_FUNCTION_INFO, vec![0x00, 0x04, 0x17, 0x50, 0x02, 0x01, 0x00, 0x00]).pack();
+    let log = log(&[
+        (true, acl(0b10, &l2cap(&discovery))),
+        // Another characteristic, ignored even though it looks like a frame
+        (false, att(0x52, 0x0020, &request)),
+        (false, att(0x52, 0x0010, &request)),
+        (true, att(0x1b, 0x0012, &response[..10])),
+        (true, att(0x1b, 0x0012, &response[10..])),
+    ]);
+
+    let recording = btsnoop::import(&log).unwrap();
+    assert_eq!(recording.frames.len(), 2);
+    assert_eq!(recording.frames[0].direction, FrameDirection::Sent);
+    assert_eq!(recording.frames[0].data, request);
+    assert_eq!(recording.frames[1].direction, FrameDirection::Received);
+    assert_eq!(recording.frames[1].data, response);
+    assert_eq!(recording.frames[1].time.as_micros(), 3000);
+}
+
+#[test]
+fn joins_acl_fragments_without_discovery() {
+    let response = Packet::response(SID::CHECK_CAMERA_STATUS, vec![0x00, 0xff, 0xff]).pack();
+    let mut pdu = vec![0x1b, 0x12, 0x00];
+    pdu.extend(&response);
+    let l2cap = l2cap(&pdu);
+    let log = log(&[(true, acl(0b10, &l2cap[..6])), (true, acl(0b01, &l2cap[6..]))]);
+
+    let recording = btsnoop::import(&log).unwrap();
+    assert_eq!(recording.frames, vec![Frame { time: recording.frames[0].time, direction: FrameDirection::Received, data: response }]);
+    assert!(btsnoop::import(b"not a log").is_err());
+}
+
+#[test]
+fn timeline_decodes_payloads_and_flags_unknowns() {
+    let recording = Recording {
+        frames: vec![
+            Frame::received(Packet::response(SID::SUPPORT_FUNCTION_AND_VERSION_INFO, vec![0, 0, 0, 0, 0, 0, 2, 0]).pack()),
+            Frame::received(Packet::response(SID::SUPPORT_FUNCTION_INFO, vec![0x00, 0x04, 0x17, 0x50, 0x02, 0x01, 0x00, 0x00, 0x02]).pack()),
+            Frame::sent(unknown_frame(0x8888, &[0x01])),
+            Frame::received(Packet::response(SID::CHECK_CAMERA_STATUS, vec![0x00, 0x00, 0xff, 0xff, 0x42]).pack()),
+            Frame::sent(Packet::with_data(SID::SUPPORT_FUNCTION_INFO, vec![0x09]).pack()),
+            Frame::received(Packet::response(SID::AUTO_SLEEP_SETTINGS, vec![0x81]).pack()),
+            Frame::received(Packet::response(SID::AUTO_SLEEP_SETTINGS, vec![0x83]).pack()),
+        ],
+    };
+    let timeline = timeline::annotate(&recording);
+
+    assert!(timeline[1].decoded.as_ref().unwrap().contains("camera_status: Some("));
+    assert!(timeline[1].warnings.is_empty());
+    assert_eq!(timeline[2].warnings, vec!["unknown SID 0x8888"]);
+    assert!(timeline[3].decoded.as_ref().unwrap().contains("NO_ERROR"));
+    assert_eq!(timeline[3].warnings, vec!["unknown trailing bytes 42"]);
+    assert_eq!(timeline[4].warnings, vec!["unknown info type 9"]);
+    assert!(timeline[4].to_string().ends_with("! unknown info type 9"));
+    assert_eq!(timeline[5].decoded.as_deref(), Some("NO_DATA"));
+    assert!(timeline[5].warnings.is_empty());
+    assert_eq!(timeline[6].decoded.as_deref(), Some("INVALID_PARAMETER"));
+    assert_eq!(timeline[6].warnings, vec!["unverified status 0x83"]);
+}
@@ -0,0 +1,166 @@
+use instax_pal::codec::Decode;
+use instax_pal::*;
+
+// SUPPORT_FUNCTION_INFO response payloads for CAMERA_FUNCTION_INFO
+// Synthetic, built from the layout documented in lib.rs rather than captured;
+// the version 2 status byte in particular is not confirmed by a real camera
+struct Case {
+    name: &'static str,
+    version: u8,
+    payload: &'static [u8],
+    expected: CameraFunctionInfo,
+}
+
+fn cases() -> Vec<Case> {
+    vec![
+        Case {
+            name: "idle on battery, no error",
+            version: 2,
+            payload: &[0x00, 0x04, 0x03, 0x64, 0x00, 0x00, 0xff, 0xff, 0x00],
+            expected: CameraFunctionInfo {
+                battery_level: 3,
+                is_charging: false,
+                battery_capacity: 100,
+                auto_image_transfer_count: 0,
+                charger_state: ChargerStateType::NOT_CHARGING,
+                camera_error_type: CameraErrorType::NO_ERROR,
+                camera_status: Some(CameraStatusType::IDLE),
+            },
+        },
+        Case {
+            name: "charging with queued images",
+            version: 2,
+            payload: &[0x00, 0x04, 0x12, 0x2d, 0x05, 0x01, 0xff, 0xff, 0x03],
+            expected: CameraFunctionInfo {
+                battery_level: 2,
+                is_charging: true,
+                battery_capacity: 45,
+                auto_image_transfer_count: 5,
+                charger_state: ChargerStateType::CHARGING,
+                camera_error_type: CameraErrorType::NO_ERROR,
+                camera_status: Some(CameraStatusType::TRANSFERRING),
+            },
+        },
+        Case {
+            name: "charge complete",
+            version: 2,
+            payload: &[0x00, 0x04, 0x04, 0x64, 0x00, 0x02, 0xff, 0xff, 0x00],
+            expected: CameraFunctionInfo {
+                battery_level: 4,
+                is_charging: false,
+                battery_capacity: 100,
+                auto_image_transfer_count: 0,
+                charger_state: ChargerStateType::CHARGE_COMPLETE,
+                camera_error_type: CameraErrorType::NO_ERROR,
+                camera_status: Some(CameraStatusType::IDLE),
+            },
+        },
+        Case {
+            name: "media full while shooting",
+            version: 2,
+            payload: &[0x00, 0x04, 0x01, 0x0a, 0x00, 0x00, 0x00, 0x07, 0x01],
+            expected: CameraFunctionInfo {
+                battery_level: 1,
+                is_charging: false,
+                battery_capacity: 10,
+                auto_image_transfer_count: 0,
+                charger_state: ChargerStateType::NOT_CHARGING,
+                camera_error_type: CameraErrorType::MEDIA_CAPACITY_FULL,
+                camera_status: Some(CameraStatusType::SHOOTING),
+            },
+        },
+        Case {
+            name: "battery error code zero",
+            version: 2,
+            payload: &[0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00],
+            expected: CameraFunctionInfo {
+                battery_level: 0,
+                is_charging: false,
+                battery_capacity: 0,
+                auto_image_transfer_count: 0,
+                charger_state: ChargerStateType::CHARGE_ERROR,
+                camera_error_type: CameraErrorType::BATTERY_NG_ERROR,
+                camera_status: Some(CameraStatusType::IDLE),
+            },
+        },
+        Case {
+            name: "unknown codes fall back",
+            version: 2,
+            payload: &[0x00, 0x04, 0xe3, 0x32, 0x00, 0x09, 0x00, 0x63, 0x7f],
+            expected: CameraFunctionInfo {
+                battery_level: 3,
+                is_charging: false,
+                battery_capacity: 50,
+                auto_image_transfer_count: 0,
+                charger_state: ChargerStateType::UNKNOWN,
+                camera_error_type: CameraErrorType::RESERVED_ERROR,
+                camera_status: Some(CameraStatusType::UNKNOWN),
+            },
+        },
+        Case {
+            name: "version 1 has no camera status",
+            version: 1,
+            payload: &[0x00, 0x04, 0x03, 0x50, 0x02, 0x00, 0xff, 0xff],
+            expected: CameraFunctionInfo {
+                battery_level: 3,
+                is_charging: false,
+                battery_capacity: 80,
+                auto_image_transfer_count: 2,
+                charger_state: ChargerStateType::NOT_CHARGING,
+                camera_error_type: CameraErrorType::NO_ERROR,
+                camera_status: None,
+            },
+        },
+    ]
+}
+
+#[test]
+fn decodes_camera_function_info() {
+    for case in cases() {
+        let info = CameraFunctionInfo::decode(case.payload, case.version)
+            .unwrap_or_else(|err| panic!("{}: {}", case.name, err));
+        assert_eq!(info, case.expected, "{}", case.name);
+    }
+}
+
+#[test]
+fn decodes_through_support_function_info_response() {
+    let versions = SupportFunctionVersionInfo::decode(&[0, 1, 1, 1, 0, 0, 2, 1]).unwrap();
+    for case in cases().into_iter().filter(|case| case.version == 2) {
+        match SupportFunctionInfoResponse::decode(case.payload, &versions) {
+            Ok(SupportFunctionInfoResponse::CameraFunctionInfo(info)) => assert_eq!(info, case.expected, "{}", case.name),
+            other => panic!("{}: unexpected {:?}", case.name, other),
+        }
+    }
+}
+
+#[test]
+fn status_byte_depends_on_layout_version() {
+    let payload: &[u8] = &[0x00, 0x04, 0x03, 0x64, 0x00, 0x00, 0xff, 0xff, 0x04];
+    assert_eq!(CameraFunctionInfo::decode(payload, 2).unwrap().camera_status, Some(CameraStatusType::PRINTING));
+    assert_eq!(CameraFunctionInfo::decode(payload, 1).unwrap().camera_status, None);
+}
+
+#[test]
+fn rejects_short_payloads() {
+    let short: &[u8] = &[0x00, 0x04, 0x03, 0x64, 0x00, 0x00, 0xff, 0xff];
+    assert!(matches!(
+        CameraFunctionInfo::decode(short, 2),
+        Err(DecodeError::TooShort { expected: 9, actual: 8 })
+    ));
+    assert!(matches!(
+        CameraFunctionInfo::decode(&short[..7], 1),
+        Err(DecodeError::TooShort { expected: 8, actual: 7 })
+    ));
+}
+
+#[test]
+fn rejects_unknown_versions() {
+    let payload: &[u8] = &[0x00, 0x04, 0x03, 0x64, 0x00, 0x00, 0xff, 0xff, 0x00];
+    for version in [0, 3, 255] {
+        assert!(matches!(
+            CameraFunctionInfo::decode(payload, version),
+            Err(DecodeError::UnsupportedVersion { info_type: SupportFunctionInfoType::CAMERA_FUNCTION_INFO, .. })
+        ));
+    }
+}
@@ -0,0 +1,53 @@
+use instax_pal::capabilities::Capabilities;
+use instax_pal::*;
+
+fn versions(device_info: u8, printer: u8, camera: u8) -> SupportFunctionVersionInfo {
+    SupportFunctionVersionInfo {
+        support_function_info: 0,
+        device_info_version: device_info,
+        support_img_info_version: 0,
+        battery_info_version: 0,
+        printer_func_info_version: printer,
+        printer_history_info_version: 0,
+        camera_func_info_version: camera,
+        camera_history_info_version: 0,
+    }
+}
+
+fn image_support(size: u32) -> ImageSupportInfo {
+    ImageSupportInfo { width: 800, height: 600, pic_type: 0, pic_option: 0, size }
+}
+
+#[test]
+fn printer_only_device_keeps_settings_and_time() {
+    let capabilities = Capabilities::new(versions(1, 1, 0), None, None);
+    for sid in [SID::PARAMETER_RW, SID::TIME_SETTING, SID::CAMERA_SETTINGS, SID::URL_UPLOAD_INFO, SID::CAMERA_LOG_DATE_START] {
+        assert!(capabilities.supports(sid), "{:?}", sid);
+    }
+    for sid in [SID::EXECUTE_CAMERA_COMMAND, SID::CHECK_CAMERA_STATUS, SID::LIVE_VIEW_START, SID::INTERVAL_RECORD_START] {
+        assert!(!capabilities.supports(sid), "{:?}", sid);
+    }
+    assert!(capabilities.supports(SID::PRINT_IMAGE));
+    assert!(capabilities.supports(SID::DEVICE_INFO_SERVICE));
+}
+
+#[test]
+fn camera_without_printer_or_image_format() {
+    let capabilities = Capabilities::new(versions(0, 0, 1), None, None);
+    assert!(capabilities.supports(SID::EXECUTE_CAMERA_COMMAND));
+    assert!(!capabilities.supports(SID::PRINT_IMAGE));
+    assert!(!capabilities.supports(SID::POST_VIEW_PRINT));
+    assert!(!capabilities.supports(SID::DEVICE_INFO_SERVICE));
+    assert!(!capabilities.supports(SID::IMAGE_AUTO_UPLOAD_DATA));
+    assert!(!capabilities.supports(SID::LIVE_VIEW_RECEIVE));
+}
+
+#[test]
+fn image_transfer_needs_a_reported_size() {
+    let empty = Capabilities::new(versions(0, 0, 1), Some(image_support(0)), None);
+    assert!(!empty.supports(SID::URL_PICTURE_UPLOAD));
+    let capabilities = Capabilities::new(versions(0, 0, 1), Some(image_support(0x10000)), None);
+    for sid in [SID::URL_PICTURE_UPLOAD, SID::IMAGE_MANUAL_UPLOAD_START, SID::IMAGE_AUTO_UPLOAD_DATA, SID::LIVE_VIEW_RECEIVE] {
+        assert!(capabilities.supports(sid), "{:?}", sid);
+    }
+}
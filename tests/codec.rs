@@ -0,0 +1,179 @@
+use std::fmt::Debug;
+use num_traits::FromPrimitive;
+use proptest::prelude::*;
+use proptest::sample::select;
+use instax_pal::codec::{Decode, Encode, Layout};
+use instax_pal::packet::Packet;
+use instax_pal::*;
+
+#[test]
+fn reads_and_writes_declared_layouts() {
+    let status = CameraStatus::decode(&[0x00, 0x05, 0xff, 0xff]).unwrap();
+    assert!(status.is_busy && !status.is_shooting && status.is_printing);
+    assert_eq!(status.error, CameraErrorType::NO_ERROR);
+    assert_eq!(status.encode(), vec![0x00, 0x05, 0xff, 0xff]);
+
+    let info = UrlUploadInfo::decode(&[0, 0, 0, 0, 0x01, 0x00, 0, 0, 0, 0x20]).unwrap();
+    assert_eq!((info.status, info.picture_size, info.audio_size), (ResponseStatus::OK, 256, 32));
+    assert_eq!(UrlUploadInfo::min_len(), 10);
+}
+
+#[test]
+fn rejects_short_payloads_and_unknown_enum_values() {
+    assert!(matches!(IntervalRecordStatus::decode(&[0, 1, 0]), Err(DecodeError::TooShort { expected: 6, actual: 3 })));
+    assert!(matches!(
+        ParameterReadWriteResponse::decode(&[0, 0, 0x7f, 0, 0, 0, 0]),
+        Err(DecodeError::InvalidValue { field: "setting", at: 2, value: 0x7f })
+    ));
+}
+
+#[test]
+fn encodes_requests_as_the_camera_expects() {
+    let read = ParameterReadWriteRequest {
+        setting: ReadWriteSettingType::FLASH_SETTING,
+        mode: ReadWriteSettingMode::GET_CURRENT_SETTING,
+        value: 0,
+    };
+    assert_eq!(read.encode(), vec![ReadWriteSettingType::FLASH_SETTING as u8, 0, 0, 0, 0, 0]);
+    let write = CameraSettingsRequest { setting: ReadWriteSettingType::FLASH_SETTING, value: 0x0102 };
+    assert_eq!(write.encode(), vec![ReadWriteSettingType::FLASH_SETTING as u8, 0x02, 0x01, 0, 0]);
+    let start = IntervalRecordStartRequest { mode: IntervalRecordMode::LONG_INTERVAL, shots: 300 };
+    assert_eq!(start.encode(), vec![1, 0x01, 0x2c]);
+}
+
+// The command codes are inferred, not captured; this pins them so a correction
+// from a real capture shows up as a test change
+#[test]
+fn encodes_camera_commands_as_one_byte() {
+    let frames: Vec<Vec<u8>> = [CameraCommand::SHUTTER, CameraCommand::PRINT, CameraCommand::CANCEL]
+        .into_iter()
+        .map(|command| Packet::with_payload(SID::EXECUTE_CAMERA_COMMAND, &CameraCommandRequest { command }).pack())
+        .collect();
+    for (code, frame) in frames.iter().enumerate() {
+        assert_eq!(&frame[4..6], &[0x85, 0x01], "sid");
+        assert_eq!(frame[6], code as u8);
+        assert_eq!(frame.len(), 8);
+    }
+    assert!(matches!(CameraCommandRequest::decode(&[3]), Err(DecodeError::InvalidValue { field: "camera command", at: 0, value: 3 })));
+}
+
+fn round_trip<T: Decode + Encode + PartialEq + Debug>(value: T) -> Result<(), TestCaseError> {
+    let bytes = value.encode();
+    prop_assert_eq!(T::decode(&bytes).unwrap(), value);
+    Ok(())
+}
+
+// Values of an enum decoded with Enum, i.e. without fallback
+fn known<T: FromPrimitive + Debug>() -> impl Strategy<Value = T> {
+    (0u8..16).prop_filter_map("unknown value", T::from_u8)
+}
+
+// Values of an enum decoded with EnumOr, unknown bytes become the fallback
+fn any_of<T: FromPrimitive + Copy + Debug>(fallback: T) -> impl Strategy<Value = T> {
+    any::<u8>().prop_map(move |value| T::from_u8(value).unwrap_or(fallback))
+}
+
+fn camera_error() -> impl Strategy<Value = CameraErrorType> {
+    any::<i16>().prop_map(CameraErrorType::from_code)
+}
+
+proptest! {
+    #[test]
+    fn requests_round_trip(
+        setting in select(ReadWriteSettingType::ALL.to_vec()),
+        mode in known::<ReadWriteSettingMode>(),
+        value: u32,
+        info_type in known::<SupportFunctionInfoType>(),
+        device_info_type in select(DeviceInfoType::ALL.to_vec()),
+        command in known::<CameraCommand>(),
+        interval_mode in known::<IntervalRecordMode>(),
+        shots: u16,
+        minutes: u8,
+        index: u32,
+        datestamp in "[0-9]{14}",
+        url in "\\PC*",
+    ) {
+        round_trip(ParameterReadWriteRequest { setting, mode, value })?;
+        round_trip(CameraSettingsRequest { setting, value })?;
+        round_trip(SupportFunctionInfoRequest { info_type })?;
+        round_trip(DeviceInfoRequest { info_type: device_info_type })?;
+        round_trip(CameraCommandRequest { command })?;
+        round_trip(IntervalRecordStartRequest { mode: interval_mode, shots })?;
+        round_trip(AutoSleepRequest { minutes })?;
+        round_trip(ChunkRequest { index })?;
+        round_trip(DateTimeRequest { mode: 2, datestamp })?;
+        round_trip(UrlUploadAddress { url })?;
+    }
+
+    #[test]
+    fn responses_round_trip(
+        versions: [u8; 8],
+        flags: [bool; 5],
+        small: [u8; 4],
+        sizes: [u32; 2],
+        error in camera_error(),
+        printer_state in any_of(PrinterState::UNKNOWN),
+        charger_state in any_of(ChargerStateType::UNKNOWN),
+        camera_status in any_of(CameraStatusType::UNKNOWN),
+        mode in known::<ReadWriteSettingMode>(),
+        setting in select(ReadWriteSettingType::ALL.to_vec()),
+        value: u32,
+        status in any::<u8>().prop_map(ResponseStatus::from_code),
+        datestamp in "\\PC*",
+    ) {
+        let [support, device, image, battery, printer, print_history, camera, camera_history] = versions;
+        round_trip(SupportFunctionVersionInfo {
+            support_function_info: support,
+            device_info_version: device,
+            support_img_info_version: image,
+            battery_info_version: battery,
+            printer_func_info_version: printer,
+            printer_history_info_version: print_history,
+            camera_func_info_version: camera,
+            camera_history_info_version: camera_history,
+        })?;
+        round_trip(CameraStatus { status, is_busy: flags[0], is_shooting: flags[1], is_printing: flags[2], error })?;
+        round_trip(PrinterFunctionInfo { film_remaining: small[0] & 0x0f, is_film_loaded: flags[3], printer_state })?;
+        round_trip(CameraFunctionInfo {
+            battery_level: small[0] & 0x0f,
+            is_charging: flags[3],
+            battery_capacity: small[1],
+            auto_image_transfer_count: small[2],
+            charger_state,
+            camera_error_type: error,
+            camera_status: Some(camera_status),
+        })?;
+        round_trip(BatteryInfo { battery_level: small[0], battery_capacity: small[1], charger_type: small[2], charger_state: small[3] })?;
+        round_trip(UrlUploadInfo { status, picture_size: sizes[0], audio_size: sizes[1] })?;
+        round_trip(IntervalRecordStatus { status, is_recording: flags[4], shots_taken: value as u16, shots_planned: (value >> 16) as u16 })?;
+        round_trip(ParameterReadWriteResponse { status, mode, setting, value })?;
+        round_trip(StatusReply { status })?;
+        round_trip(DateTimeResponse { status, datestamp })?;
+    }
+
+    #[test]
+    fn support_function_info_envelope_round_trips(film in 0u8..16, loaded: bool, printer_state in any_of(PrinterState::UNKNOWN)) {
+        let versions = SupportFunctionVersionInfo::decode(&[1; 8]).unwrap();
+        let response = SupportFunctionInfoResponse::PrinterFunctionInfo(PrinterFunctionInfo {
+            film_remaining: film,
+            is_film_loaded: loaded,
+            printer_state,
+        });
+        prop_assert_eq!(SupportFunctionInfoResponse::decode(&response.encode(), &versions).unwrap(), response);
+    }
+
+    #[test]
+    fn decoding_any_payload_never_panics(bytes in proptest::collection::vec(any::<u8>(), 0..16)) {
+        let _ = CameraStatus::decode(&bytes);
+        let _ = CameraFunctionInfo::decode(&bytes, 2);
+        let _ = ParameterReadWriteResponse::decode(&bytes);
+        let _ = DeviceInfoEntry::decode(&bytes);
+        let _ = DateTimeRequest::decode(&bytes);
+        if let Ok(info) = UrlUploadInfo::decode(&bytes) {
+            // The unknown byte is not kept and encodes as zero
+            let mut expected = bytes[..10].to_vec();
+            expected[1] = 0;
+            prop_assert_eq!(info.encode(), expected);
+        }
+    }
+}
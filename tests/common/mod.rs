@@ -0,0 +1,27 @@
+// Frames shared by the replay tests
+// Each test file uses a part of them only
+#![allow(dead_code)]
+
+use instax_pal::packet::Packet;
+use instax_pal::recording::Frame;
+use instax_pal::SID;
+
+// Connect of a camera reporting the given SupportFunctionVersionInfo bytes
+// that answers no further detection queries, see handshake_with
+pub fn handshake(versions: [u8; 8]) -> Vec<Frame> {
+    handshake_with(versions, Vec::new())
+}
+
+// Connect with the detection frames that follow the version exchange, e.g.
+// IMAGE_SUPPORT_INFO or DEVICE_INFO_SERVICE queries
+// TIME_SETTING is matched by SID only, so its timestamp does not matter.
+pub fn handshake_with(versions: [u8; 8], detection: Vec<Frame>) -> Vec<Frame> {
+    let mut frames = vec![
+        Frame::sent(Packet::with_sid(SID::SUPPORT_FUNCTION_AND_VERSION_INFO).pack()),
+        Frame::received(Packet::response(SID::SUPPORT_FUNCTION_AND_VERSION_INFO, versions.to_vec()).pack()),
+    ];
+    frames.extend(detection);
+    frames.push(Frame::sent(Packet::with_data(SID::TIME_SETTING, b"220240101000000".to_vec()).pack()));
+    frames.push(Frame::received(Packet::response(SID::TIME_SETTING, vec![0, 0, 0]).pack()));
+    frames
+}
@@ -0,0 +1,77 @@
+use instax_pal::camera::Camera;
+use instax_pal::console::{Console, ConsoleCommand};
+use instax_pal::packet::{self, Packet};
+use instax_pal::recording::{Frame, Recording, ReplayTransport};
+use instax_pal::SID;
+
+mod common;
+
+use common::handshake;
+
+#[test]
+fn parses_sid_names_codes_and_payloads() {
+    let send = |sid: SID, payload: &[u8]| Ok(ConsoleCommand::Send { sid: sid as u16, payload: payload.to_vec() });
+    assert_eq!("check_camera_status".parse(), send(SID::CHECK_CAMERA_STATUS, &[]));
+    assert_eq!("0x0002 04".parse(), send(SID::SUPPORT_FUNCTION_INFO, &[0x04]));
+    assert_eq!("8401 00 00 00 01  # second chunk".parse(), send(SID::CAMERA_LOG_SUBTOTAL_DATA, &[0, 0, 0, 1]));
+    assert_eq!("0x1234 01".parse(), Ok(ConsoleCommand::Send { sid: 0x1234, payload: vec![0x01] }));
+    assert_eq!("!3".parse(), Ok(ConsoleCommand::Repeat(Some(3))));
+    assert_eq!("save probe.txt".parse(), Ok(ConsoleCommand::Save("probe.txt".to_string())));
+    assert!("0x12345".parse::<ConsoleCommand>().unwrap_err().contains("unknown command or SID 0x12345"));
+    assert!("time_setting 0".parse::<ConsoleCommand>().is_err());
+    assert!("save".parse::<ConsoleCommand>().is_err());
+}
+
+#[tokio::test]
+async fn sends_packets_and_saves_history() {
+    let status_request = Frame::sent(Packet::with_sid(SID::CHECK_CAMERA_STATUS).pack());
+    let status_response = Frame::received(Packet::response(SID::CHECK_CAMERA_STATUS, vec![0x00, 0x01, 0xff, 0xff]).pack());
+    // No camera function reported, TIME_SETTING is still sent
+    let mut frames = handshake([0; 8]);
+    frames.extend([status_request.clone(), status_response.clone(), status_request, status_response]);
+    let recording = Recording { frames };
+    let mut camera = Camera::from_transport(Box::new(ReplayTransport::new(recording))).await.unwrap();
+    let mut console = Console::new(&mut camera);
+    let mut out = Vec::new();
+
+    assert!(console.execute("CHECK_CAMERA_STATUS", &mut out).await.unwrap());
+    assert!(console.execute("!!", &mut out).await.unwrap());
+    assert!(console.execute("!5", &mut out).await.unwrap());
+    assert!(!console.execute("quit", &mut out).await.unwrap());
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(out.matches("is_busy: true").count(), 2, "{}", out);
+    assert!(out.contains("> CHECK_CAMERA_STATUS\n"));
+    assert!(out.contains("error: no such history entry"));
+    assert_eq!(console.history(), ["CHECK_CAMERA_STATUS", "CHECK_CAMERA_STATUS"]);
+
+    let script = std::env::temp_dir().join(format!("instax_pal_console_{}.txt", std::process::id()));
+    let mut out = Vec::new();
+    console.execute(&format!("save {}", script.display()), &mut out).await.unwrap();
+    let saved = std::fs::read_to_string(&script).unwrap();
+    std::fs::remove_file(&script).unwrap();
+    assert_eq!(saved, "# instax_pal console script\nCHECK_CAMERA_STATUS\nCHECK_CAMERA_STATUS\n");
+}
+
+#[tokio::test]
+async fn probes_unknown_sids_and_refuses_recursive_scripts() {
+    // Swapping Ab for aB keeps the checksum, the bytes sum up the same
+    let mut reply = packet::request_frame(0x1234, &[0x00, 0x2a]);
+    reply[0..2].copy_from_slice(b"aB");
+    let mut frames = handshake([0; 8]);
+    frames.extend([Frame::sent(packet::request_frame(0x1234, &[0x01])), Frame::received(reply)]);
+    let recording = Recording { frames };
+    let mut camera = Camera::from_transport(Box::new(ReplayTransport::new(recording))).await.unwrap();
+    let mut console = Console::new(&mut camera);
+
+    let script = std::env::temp_dir().join(format!("instax_pal_console_run_{}.txt", std::process::id()));
+    std::fs::write(&script, format!("0x1234 01\nrun {}\n", script.display())).unwrap();
+    let mut out = Vec::new();
+    let result = console.execute(&format!("run {}", script.display()), &mut out).await;
+    std::fs::remove_file(&script).unwrap();
+    assert!(result.unwrap());
+
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("sid=0x1234 payload=002a"), "{}", out);
+    assert!(out.contains("is already running"), "{}", out);
+    assert_eq!(console.history(), ["0x1234 01"]);
+}
@@ -0,0 +1,42 @@
+use instax_pal::camera::CameraError;
+use instax_pal::*;
+
+// DEVICE_INFO_SERVICE reply: status, DeviceInfoType, value
+fn reply(info_type: DeviceInfoType, value: &[u8]) -> Vec<u8> {
+    [&[0x00, info_type as u8][..], value].concat()
+}
+
+#[test]
+fn decodes_pnp_id_little_endian() {
+    let pnp_id = PnpId::from_bytes(&[0x01, 0x2e, 0x04, 0x34, 0x12, 0x00, 0x01]).unwrap();
+    assert_eq!(pnp_id, PnpId { vendor_id_source: 1, vendor_id: 0x042e, product_id: 0x1234, product_version: 0x0100 });
+    assert_eq!(PnpId::from_bytes(&[0x01, 0x2e, 0x04, 0x34, 0x12, 0x00]), None);
+}
+
+#[test]
+fn stores_device_info_replies() {
+    let mut info = DeviceInfo::default();
+    info.update_from_bytes(&reply(DeviceInfoType::MANUFACTURER_NAME, b"FUJIFILM")).unwrap();
+    info.update_from_bytes(&reply(DeviceInfoType::MODEL_NUMBER, b"PAL\0\0")).unwrap();
+    info.update_from_bytes(&reply(DeviceInfoType::SYSTEM_ID, &[0xde, 0xad, 0x00])).unwrap();
+    info.update_from_bytes(&reply(DeviceInfoType::PNP_ID, &[0x02, 0xd8, 0x04, 0x01, 0x00, 0x10, 0x00])).unwrap();
+
+    assert_eq!(info.manufacturer_name, "FUJIFILM");
+    assert_eq!(info.model_number, "PAL");
+    assert_eq!(info.system_id, vec![0xde, 0xad, 0x00]);
+    assert_eq!(info.pnp_id, Some(PnpId { vendor_id_source: 2, vendor_id: 0x04d8, product_id: 1, product_version: 0x10 }));
+}
+
+#[test]
+fn rejects_failed_and_malformed_replies() {
+    let mut info = DeviceInfo::default();
+    assert!(matches!(info.update_from_bytes(&[0x00]), Err(CameraError::Decode(DecodeError::TooShort { .. }))));
+    assert!(matches!(info.update_from_bytes(&[0x00, 0x7f, b'x']), Err(CameraError::Decode(DecodeError::InvalidValue { .. }))));
+    assert!(matches!(
+        info.update_from_bytes(&[0x84, DeviceInfoType::MANUFACTURER_NAME as u8, b'x']),
+        Err(CameraError::Unsupported(_))
+    ));
+    info.update_from_bytes(&reply(DeviceInfoType::PNP_ID, &[0x02, 0xd8])).unwrap();
+    assert_eq!(info.manufacturer_name, "");
+    assert_eq!(info.pnp_id, None);
+}
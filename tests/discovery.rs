@@ -0,0 +1,36 @@
+use bluer::Address;
+use instax_pal::advertisement::Advertisement;
+use instax_pal::discovery::{DeviceSelector, DiscoveredCamera};
+
+fn camera(address: [u8; 6], alias: &str) -> DiscoveredCamera {
+    DiscoveredCamera {
+        address: Address::new(address),
+        name: Some(alias.to_string()),
+        alias: alias.to_string(),
+        rssi: Some(-60),
+        is_paired: false,
+        advertisement: Advertisement::default(),
+    }
+}
+
+#[test]
+fn parses_addresses_before_aliases() {
+    let address = Address::new([0xfa, 0xab, 0xbc, 0x00, 0x11, 0x22]);
+    assert_eq!("FA:AB:BC:00:11:22".parse(), Ok(DeviceSelector::Address(address)));
+    assert_eq!("fa:ab:bc:00:11:22".parse(), Ok(DeviceSelector::Address(address)));
+    assert_eq!("INSTAX-12345678".parse(), Ok(DeviceSelector::Alias("INSTAX-12345678".to_string())));
+    assert_eq!("FA:AB:BC:00:11".parse(), Ok(DeviceSelector::Alias("FA:AB:BC:00:11".to_string())));
+}
+
+#[test]
+fn matches_by_address_or_alias() {
+    let pal = camera([0xfa, 0xab, 0xbc, 0x00, 0x11, 0x22], "INSTAX-12345678");
+    let other = camera([0xfa, 0xab, 0xbc, 0x00, 0x11, 0x33], "kitchen");
+    assert!(DeviceSelector::Any.matches(&pal) && DeviceSelector::Any.matches(&other));
+
+    let by_address: DeviceSelector = "FA:AB:BC:00:11:22".parse().unwrap();
+    assert!(by_address.matches(&pal) && !by_address.matches(&other));
+
+    let by_alias = DeviceSelector::Alias("Kitchen".to_string());
+    assert!(by_alias.matches(&other) && !by_alias.matches(&pal));
+}
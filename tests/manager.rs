@@ -0,0 +1,38 @@
+use instax_pal::camera::Camera;
+use instax_pal::manager::CameraManager;
+use instax_pal::recording::{Recording, ReplayTransport};
+
+mod common;
+
+use common::handshake;
+
+// Cameras from a transport have no address, Address::any() stands in
+async fn camera() -> Camera {
+    let frames = handshake([0; 8]);
+    Camera::from_transport(Box::new(ReplayTransport::new(Recording { frames }))).await.unwrap()
+}
+
+#[tokio::test]
+async fn keeps_aliases_unique() {
+    let mut manager = CameraManager::new();
+    assert_eq!(manager.add("kitchen", camera().await), "kitchen");
+    assert_eq!(manager.add("kitchen", camera().await), "kitchen (00:00:00:00:00:00)");
+    assert_eq!(manager.add("garden", camera().await), "garden");
+    assert_eq!(manager.aliases().collect::<Vec<_>>(), ["garden", "kitchen", "kitchen (00:00:00:00:00:00)"]);
+}
+
+#[tokio::test]
+async fn looks_up_by_alias_then_address() {
+    let mut manager = CameraManager::new();
+    manager.add("kitchen", camera().await);
+    manager.add("garden", camera().await);
+    assert!(manager.get_mut("kitchen").is_some());
+    assert!(manager.get_mut("cellar").is_none());
+    assert!(manager.get_mut("00:00:00:00:00:00").is_some());
+    assert!(manager.get_mut("11:22:33:44:55:66").is_none());
+
+    assert!(manager.remove("kitchen").is_some());
+    assert!(manager.remove("kitchen").is_none());
+    assert!(manager.remove("00:00:00:00:00:00").is_some());
+    assert!(manager.is_empty());
+}
@@ -0,0 +1,68 @@
+use instax_pal::packet::{answers, describe, Packet};
+use instax_pal::{DecodeError, SID};
+
+#[test]
+fn labels_request_fields() {
+    let frame = Packet::with_type(SID::SUPPORT_FUNCTION_INFO, 0x02).pack();
+    assert_eq!(
+        describe(&frame),
+        format!("Ab size=8 sid=SUPPORT_FUNCTION_INFO(0x0002) payload=02 checksum=0x{:02x} ok", frame[7])
+    );
+}
+
+#[test]
+fn flags_bad_checksum_and_size_mismatch() {
+    let mut frame = Packet::with_sid(SID::CHECK_CAMERA_STATUS).pack();
+    frame[2..4].copy_from_slice(&9u16.to_be_bytes());
+    assert!(describe(&frame).starts_with("Ab size=9 (frame has 7) sid=CHECK_CAMERA_STATUS(0x8500) checksum="));
+    assert!(describe(&frame).ends_with(" bad"));
+}
+
+#[test]
+fn short_and_unknown_frames_do_not_panic() {
+    assert_eq!(describe(&[0x61]), "short frame 61");
+    assert_eq!(describe(&[0x61, 0x42, 0x00]), "aB short frame 614200");
+    assert_eq!(describe(&[0x61, 0x42, 0x00, 0x07, 0xfe, 0xfe, 0x00]).split(" checksum").next(), Some("aB size=7 sid=0xfefe"));
+}
+
+#[test]
+fn unpacks_reply_frames() {
+    let empty = Packet::unpack(&[0x61, 0x42, 0x00, 0x07, 0x85, 0x00, 0xd0]).unwrap();
+    assert_eq!(empty.sid, SID::CHECK_CAMERA_STATUS);
+    assert!(empty.data.is_empty());
+    let status = Packet::unpack(&[0x61, 0x42, 0x00, 0x08, 0x85, 0x01, 0x81, 0x4d]).unwrap();
+    assert_eq!((status.sid, status.data), (SID::EXECUTE_CAMERA_COMMAND, vec![0x81]));
+    let payload = Packet::unpack(&[0x61, 0x42, 0x00, 0x09, 0x80, 0x12, 0x00, 0x01, 0xc0]).unwrap();
+    assert_eq!(payload.data, vec![0x00, 0x01]);
+}
+
+#[test]
+fn rejects_frames_it_cannot_unpack() {
+    assert!(matches!(Packet::unpack(&[0x61, 0x42, 0x00, 0x07, 0x85, 0x00]), Err(DecodeError::TooShort { expected: 7, actual: 6 })));
+    assert!(matches!(Packet::unpack(&[0x61, 0x42, 0x00, 0x07, 0xfe, 0xfe, 0x00]), Err(DecodeError::UnknownSid(0xfefe))));
+    assert!(matches!(Packet::unpack(&[0x12, 0x34, 0x00, 0x07, 0x85, 0x00, 0x00]), Err(DecodeError::UnknownDirection(0x1234))));
+}
+
+#[test]
+fn matches_replies_by_sid_and_info_type() {
+    let camera_info = Packet::with_type(SID::SUPPORT_FUNCTION_INFO, 4).pack();
+    assert!(answers(&camera_info, &Packet::response(SID::SUPPORT_FUNCTION_INFO, vec![0, 4, 0x17]).pack()));
+    assert!(!answers(&camera_info, &Packet::response(SID::SUPPORT_FUNCTION_INFO, vec![0, 1, 0x50]).pack()));
+    assert!(answers(&camera_info, &Packet::response(SID::SUPPORT_FUNCTION_INFO, vec![0x84]).pack()));
+    assert!(!answers(&camera_info, &Packet::response(SID::DEVICE_INFO_SERVICE, vec![0, 4, 0x41]).pack()));
+
+    let status = Packet::with_sid(SID::CHECK_CAMERA_STATUS).pack();
+    assert!(answers(&status, &Packet::response(SID::CHECK_CAMERA_STATUS, vec![0, 0, 0xff, 0xff]).pack()));
+    assert!(!answers(&status, &[0x61, 0x42]));
+}
+
+#[test]
+fn looks_up_sids_by_name() {
+    assert_eq!(SID::from_name("check_camera_status"), Some(SID::CHECK_CAMERA_STATUS));
+    assert_eq!(SID::from_name("INTERVAL_RECORD_STOP"), Some(SID::INTERVAL_RECORD_STOP));
+    assert_eq!(SID::from_name("unknown"), None);
+    for (sid, name) in SID::ALL {
+        assert_eq!(format!("{:?}", sid), *name);
+    }
+    assert!(SID::all().all(|sid| sid != SID::UNKNOWN));
+}
@@ -0,0 +1,46 @@
+use instax_pal::*;
+
+// Synthetic payloads built from the inferred layout in lib.rs, not captured
+// from a camera; they pin the decoding until a real capture is available
+#[test]
+fn decodes_printer_function_info() {
+    let cases: [(&str, &[u8], PrinterFunctionInfo); 3] = [
+        (
+            "loaded pack, idle",
+            &[0x00, 0x03, 0x1a, 0x00],
+            PrinterFunctionInfo { film_remaining: 10, is_film_loaded: true, printer_state: PrinterState::IDLE },
+        ),
+        (
+            "no pack",
+            &[0x00, 0x03, 0x00, 0x02],
+            PrinterFunctionInfo { film_remaining: 0, is_film_loaded: false, printer_state: PrinterState::NO_FILM },
+        ),
+        (
+            "unknown state falls back",
+            &[0x00, 0x03, 0xe4, 0x7f],
+            PrinterFunctionInfo { film_remaining: 4, is_film_loaded: false, printer_state: PrinterState::UNKNOWN },
+        ),
+    ];
+    for (name, payload, expected) in cases {
+        let info = PrinterFunctionInfo::decode(payload, 1).unwrap_or_else(|err| panic!("{}: {}", name, err));
+        assert_eq!(info, expected, "{}", name);
+    }
+}
+
+#[test]
+fn decodes_print_history_info() {
+    let info = PrintHistoryInfo::decode(&[0x00, 0x04, 0x00, 0x00, 0x01, 0x2c], 1).unwrap();
+    assert_eq!(info.total_print_num, 300);
+}
+
+#[test]
+fn rejects_short_and_unversioned_payloads() {
+    assert!(matches!(
+        PrinterFunctionInfo::decode(&[0x00, 0x03, 0x1a], 1),
+        Err(DecodeError::TooShort { expected: 4, actual: 3 })
+    ));
+    assert!(matches!(
+        PrinterFunctionInfo::decode(&[0x00, 0x03, 0x1a, 0x00], 2),
+        Err(DecodeError::UnsupportedVersion { info_type: SupportFunctionInfoType::PRINTER_FUNCTION_INFO, version: 2 })
+    ));
+}
@@ -0,0 +1,45 @@
+use instax_pal::profile::{Profile, ProfileError, SettingDiff};
+use instax_pal::ReadWriteSettingType;
+
+fn profile(settings: &[(ReadWriteSettingType, u32)]) -> Profile {
+    Profile { settings: settings.iter().copied().collect() }
+}
+
+#[test]
+fn toml_round_trip() {
+    let text = "# exported\n[settings]\nFLASH_SETTING = 1   # auto\n\nVOLUME_SETTING = 3\n";
+    let parsed = Profile::from_toml(text).unwrap();
+    assert_eq!(parsed, profile(&[(ReadWriteSettingType::FLASH_SETTING, 1), (ReadWriteSettingType::VOLUME_SETTING, 3)]));
+    assert_eq!(Profile::from_toml(&parsed.to_toml()).unwrap(), parsed);
+
+    assert!(matches!(Profile::from_toml("[settings]\nFLASH_SETTING"), Err(ProfileError::Syntax(2, _))));
+    assert!(matches!(Profile::from_toml("NO_SUCH_SETTING = 1"), Err(ProfileError::UnknownSetting(1, _))));
+    assert!(matches!(Profile::from_toml("FLASH_SETTING = -1"), Err(ProfileError::InvalidValue(1, _))));
+}
+
+#[test]
+fn diff_lists_changed_and_missing_settings() {
+    let left = profile(&[(ReadWriteSettingType::FLASH_SETTING, 1), (ReadWriteSettingType::VOLUME_SETTING, 3)]);
+    let right = profile(&[(ReadWriteSettingType::FLASH_SETTING, 1), (ReadWriteSettingType::VOLUME_SETTING, 5), (ReadWriteSettingType::EXPOSURE_SETTING, 2)]);
+    let diff = left.diff(&right);
+    assert_eq!(diff.len(), 2);
+    assert!(diff.contains(&SettingDiff { setting: ReadWriteSettingType::VOLUME_SETTING, left: Some(3), right: Some(5) }));
+    assert!(diff.contains(&SettingDiff { setting: ReadWriteSettingType::EXPOSURE_SETTING, left: None, right: Some(2) }));
+    assert!(left.diff(&left).is_empty());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json_round_trip_through_files() {
+    let original = profile(&[(ReadWriteSettingType::FLASH_SETTING, 1), (ReadWriteSettingType::VOLUME_SETTING, 3)]);
+    assert_eq!(Profile::from_json(&original.to_json()).unwrap(), original);
+    assert!(matches!(Profile::from_json("{\"settings\": []}"), Err(ProfileError::Json(_))));
+
+    let path = std::env::temp_dir().join(format!("instax_pal_profile_{}.json", std::process::id()));
+    original.save(&path).unwrap();
+    let saved = std::fs::read_to_string(&path).unwrap();
+    let loaded = Profile::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(saved.trim_start().starts_with('{'));
+    assert_eq!(loaded, original);
+}
@@ -0,0 +1,14 @@
+use std::time::Duration;
+use instax_pal::connection::ReconnectConfig;
+
+#[test]
+fn backoff_doubles_up_to_max_delay() {
+    let config = ReconnectConfig {
+        max_attempts: 10,
+        initial_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(10),
+    };
+    let delays: Vec<u64> = (1..=6).map(|attempt| config.delay(attempt).as_secs()).collect();
+    assert_eq!(delays, vec![1, 2, 4, 8, 10, 10]);
+    assert_eq!(config.delay(u32::MAX), Duration::from_secs(10));
+}
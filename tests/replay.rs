@@ -0,0 +1,302 @@
+use futures::future::{self, BoxFuture};
+use instax_pal::camera::{Camera, CameraError};
+use instax_pal::connection::ConnectionState;
+use instax_pal::packet::Packet;
+use instax_pal::recording::{Frame, Recording, ReplayTransport};
+use instax_pal::transport::Transport;
+use instax_pal::{CameraCommand, CameraCommandRequest, CameraLogType, ChargerStateType, ReadWriteSettingType, ResponseStatus, SupportFunctionInfoResponse, SupportFunctionInfoType, SID};
+
+mod common;
+
+use common::{handshake, handshake_with};
+
+// A camera supporting CAMERA_FUNCTION_INFO v1 only
+const CAMERA_ONLY: [u8; 8] = [0, 0, 0, 0, 0, 0, 1, 0];
+
+#[tokio::test]
+async fn replays_recorded_session() {
+    let mut frames = handshake(CAMERA_ONLY);
+    frames.push(Frame::sent(Packet::with_type(SID::SUPPORT_FUNCTION_INFO, SupportFunctionInfoType::CAMERA_FUNCTION_INFO as u8).pack()));
+    frames.push(Frame::received(Packet::response(SID::SUPPORT_FUNCTION_INFO, vec![0, 4, 0x17, 80, 2, 1, 0, 0]).pack()));
+    let recording = Recording::from_text(&Recording { frames }.to_text()).unwrap();
+
+    let mut camera = Camera::from_transport(Box::new(ReplayTransport::new(recording))).await.unwrap();
+    match camera.support_function_info(SupportFunctionInfoType::CAMERA_FUNCTION_INFO).await.unwrap() {
+        SupportFunctionInfoResponse::CameraFunctionInfo(info) => {
+            assert_eq!(info.battery_level, 7);
+            assert!(info.is_charging);
+            assert_eq!(info.auto_image_transfer_count, 2);
+            assert_eq!(info.charger_state, ChargerStateType::CHARGING);
+        }
+        other => panic!("unexpected response {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn connects_when_device_info_fails() {
+    let frames = handshake_with([0, 1, 0, 0, 0, 0, 1, 0], vec![
+        Frame::sent(Packet::with_data(SID::DEVICE_INFO_SERVICE, vec![0]).pack()),
+        Frame::received(Packet::response(SID::DEVICE_INFO_SERVICE, vec![0x84]).pack()),
+    ]);
+    let camera = Camera::from_transport(Box::new(ReplayTransport::new(Recording { frames }))).await.unwrap();
+    let capabilities = camera.capabilities().unwrap();
+    assert!(capabilities.device_info);
+    assert_eq!(capabilities.model, None);
+}
+
+#[tokio::test]
+async fn skips_replies_to_other_commands() {
+    let mut frames = handshake(CAMERA_ONLY);
+    frames.push(Frame::sent(Packet::with_sid(SID::CHECK_CAMERA_STATUS).pack()));
+    frames.push(Frame::received(vec![0x61, 0x42, 0x00, 0x07, 0xfe, 0xfe, 0x00]));
+    frames.push(Frame::received(Packet::response(SID::TIME_SETTING, vec![0, 0, 0]).pack()));
+    frames.push(Frame::received(Packet::response(SID::CHECK_CAMERA_STATUS, vec![0x00, 0x01, 0xff, 0xff]).pack()));
+
+    let mut camera = Camera::from_transport(Box::new(ReplayTransport::new(Recording { frames }))).await.unwrap();
+    assert!(camera.check_camera_status().await.unwrap().is_busy);
+}
+
+#[tokio::test]
+async fn skips_late_replies_for_another_info_type() {
+    let battery = SupportFunctionInfoType::BATTERY_INFO as u8;
+    let mut frames = handshake([0, 0, 0, 1, 0, 0, 1, 0]);
+    frames.push(Frame::sent(Packet::with_type(SID::SUPPORT_FUNCTION_INFO, battery).pack()));
+    // A keepalive CAMERA_FUNCTION_INFO reply that came in after its timeout
+    frames.push(Frame::received(Packet::response(SID::SUPPORT_FUNCTION_INFO, vec![0, 4, 0x17, 80, 2, 1, 0, 0]).pack()));
+    frames.push(Frame::received(Packet::response(SID::SUPPORT_FUNCTION_INFO, vec![0, battery, 90, 100, 1, 0]).pack()));
+
+    let mut camera = Camera::from_transport(Box::new(ReplayTransport::new(Recording { frames }))).await.unwrap();
+    match camera.support_function_info(SupportFunctionInfoType::BATTERY_INFO).await.unwrap() {
+        SupportFunctionInfoResponse::BatteryInfo(info) => assert_eq!(info.battery_level, 90),
+        other => panic!("unexpected response {:?}", other),
+    }
+}
+
+// The recording ends right after the command, as if the link dropped before the
+// reply. A camera from a transport cannot reconnect, so a resend shows up as
+// the Failed state of the reconnect attempt.
+async fn disconnect_after(packet: Packet) -> (Camera, tokio::sync::broadcast::Receiver<ConnectionState>) {
+    let mut frames = handshake(CAMERA_ONLY);
+    frames.push(Frame::sent(packet.pack()));
+    let camera = Camera::from_transport(Box::new(ReplayTransport::new(Recording { frames }))).await.unwrap();
+    let events = camera.subscribe();
+    (camera, events)
+}
+
+fn states(events: &mut tokio::sync::broadcast::Receiver<ConnectionState>) -> Vec<ConnectionState> {
+    std::iter::from_fn(|| events.try_recv().ok()).collect()
+}
+
+#[tokio::test]
+async fn retries_only_idempotent_requests() {
+    let (mut camera, mut events) = disconnect_after(Packet::with_sid(SID::CHECK_CAMERA_STATUS)).await;
+    assert!(matches!(camera.check_camera_status().await, Err(CameraError::Disconnected)));
+    assert_eq!(states(&mut events), [ConnectionState::Disconnected, ConnectionState::Failed]);
+
+    let shutter = Packet::with_payload(SID::EXECUTE_CAMERA_COMMAND, &CameraCommandRequest { command: CameraCommand::SHUTTER });
+    let (mut camera, mut events) = disconnect_after(shutter).await;
+    assert!(matches!(camera.execute_camera_command(CameraCommand::SHUTTER).await, Err(CameraError::Disconnected)));
+    assert_eq!(states(&mut events), [ConnectionState::Disconnected]);
+}
+
+#[tokio::test]
+async fn shut_down_and_reset_accept_a_dropped_link() {
+    let (mut camera, mut events) = disconnect_after(Packet::with_sid(SID::SHUT_DOWN)).await;
+    camera.shut_down().await.unwrap();
+    assert_eq!(states(&mut events), [ConnectionState::Disconnected]);
+
+    let (mut camera, _) = disconnect_after(Packet::with_sid(SID::RESET)).await;
+    camera.reset().await.unwrap();
+}
+
+// A camera that stops answering once the recording runs out
+struct SilentTransport(ReplayTransport);
+
+impl Transport for SilentTransport {
+    fn send(&mut self, data: Vec<u8>) -> BoxFuture<'_, Result<(), CameraError>> {
+        self.0.send(data)
+    }
+
+    fn receive(&mut self) -> BoxFuture<'_, Option<Vec<u8>>> {
+        let frame = self.0.receive();
+        Box::pin(async move {
+            match frame.await {
+                Some(frame) => Some(frame),
+                None => future::pending().await,
+            }
+        })
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn times_out_without_a_reply() {
+    let mut frames = handshake(CAMERA_ONLY);
+    frames.push(Frame::sent(Packet::with_sid(SID::CHECK_CAMERA_STATUS).pack()));
+    let transport = SilentTransport(ReplayTransport::new(Recording { frames }));
+
+    let mut camera = Camera::from_transport(Box::new(transport)).await.unwrap();
+    assert!(matches!(camera.check_camera_status().await, Err(CameraError::Timeout)));
+}
+
+#[tokio::test]
+async fn rejects_frames_the_recording_does_not_have() {
+    let mut frames = handshake(CAMERA_ONLY);
+    frames.push(Frame::sent(Packet::with_sid(SID::CHECK_CAMERA_STATUS).pack()));
+    frames.push(Frame::received(Packet::response(SID::CHECK_CAMERA_STATUS, vec![0, 0, 0, 0]).pack()));
+
+    let mut camera = Camera::from_transport(Box::new(ReplayTransport::new(Recording { frames }))).await.unwrap();
+    let result = camera.support_function_info(SupportFunctionInfoType::CAMERA_FUNCTION_INFO).await;
+    assert!(matches!(result, Err(CameraError::Replay(_))));
+}
+
+#[tokio::test]
+async fn maps_reply_status_to_errors() {
+    let mut frames = handshake(CAMERA_ONLY);
+    frames.push(Frame::sent(Packet::with_data(SID::EXECUTE_CAMERA_COMMAND, vec![CameraCommand::SHUTTER as u8]).pack()));
+    frames.push(Frame::received(Packet::response(SID::EXECUTE_CAMERA_COMMAND, vec![0x00]).pack()));
+    frames.push(Frame::sent(Packet::with_data(SID::AUTO_SLEEP_SETTINGS, vec![5]).pack()));
+    frames.push(Frame::received(Packet::response(SID::AUTO_SLEEP_SETTINGS, vec![0x82]).pack()));
+    frames.push(Frame::sent(Packet::with_data(SID::AUTO_SLEEP_SETTINGS, vec![0]).pack()));
+    frames.push(Frame::received(Packet::response(SID::AUTO_SLEEP_SETTINGS, vec![0x90]).pack()));
+    frames.push(Frame::sent(Packet::with_sid(SID::CHECK_CAMERA_STATUS).pack()));
+    frames.push(Frame::received(Packet::response(SID::CHECK_CAMERA_STATUS, vec![0x82]).pack()));
+    frames.push(Frame::sent(Packet::with_data(SID::PARAMETER_RW, vec![ReadWriteSettingType::FLASH_SETTING as u8, 0, 0, 0, 0, 0]).pack()));
+    frames.push(Frame::received(Packet::response(SID::PARAMETER_RW, vec![0x83]).pack()));
+    frames.push(Frame::sent(Packet::with_sid(SID::INTERVAL_RECORD_STATUS).pack()));
+    frames.push(Frame::received(Packet::response(SID::INTERVAL_RECORD_STATUS, vec![0x81]).pack()));
+
+    let mut camera = Camera::from_transport(Box::new(ReplayTransport::new(Recording { frames }))).await.unwrap();
+    camera.execute_camera_command(CameraCommand::SHUTTER).await.unwrap();
+    assert!(matches!(camera.set_auto_sleep(5).await, Err(CameraError::Busy(SID::AUTO_SLEEP_SETTINGS))));
+    match camera.set_auto_sleep(0).await {
+        Err(CameraError::Rejected(SID::AUTO_SLEEP_SETTINGS, status)) => assert_eq!(status, ResponseStatus::ERROR(0x90)),
+        other => panic!("unexpected result {:?}", other),
+    }
+    // Replies with data fail on the status too instead of decoding it as data
+    assert!(matches!(camera.check_camera_status().await, Err(CameraError::Busy(SID::CHECK_CAMERA_STATUS))));
+    assert!(matches!(
+        camera.parameter_read(ReadWriteSettingType::FLASH_SETTING).await,
+        Err(CameraError::InvalidParameter(SID::PARAMETER_RW))
+    ));
+    assert!(matches!(camera.interval_record_status().await, Err(CameraError::NoData(SID::INTERVAL_RECORD_STATUS))));
+    assert_eq!(ResponseStatus::from_code(0x81), ResponseStatus::NO_DATA);
+    assert_eq!(ResponseStatus::NOT_SUPPORTED.code(), 0x84);
+}
+
+#[tokio::test]
+async fn confirms_setting_writes_by_reading_back() {
+    let flash = ReadWriteSettingType::FLASH_SETTING as u8;
+    let mut frames = handshake(CAMERA_ONLY);
+    for (written, read) in [(1, 1), (2, 1)] {
+        frames.push(Frame::sent(Packet::with_data(SID::CAMERA_SETTINGS, vec![flash, written, 0, 0, 0]).pack()));
+        frames.push(Frame::received(Packet::response(SID::CAMERA_SETTINGS, vec![0x00]).pack()));
+        frames.push(Frame::sent(Packet::with_data(SID::PARAMETER_RW, vec![flash, 0, 0, 0, 0, 0]).pack()));
+        frames.push(Frame::received(Packet::response(SID::PARAMETER_RW, vec![0, 0, flash, read, 0, 0, 0]).pack()));
+    }
+
+    let mut camera = Camera::from_transport(Box::new(ReplayTransport::new(Recording { frames }))).await.unwrap();
+    camera.parameter_write(ReadWriteSettingType::FLASH_SETTING, 1).await.unwrap();
+    assert!(matches!(
+        camera.parameter_write(ReadWriteSettingType::FLASH_SETTING, 2).await,
+        Err(CameraError::NotApplied { written: 2, read: 1, .. })
+    ));
+}
+
+#[tokio::test]
+async fn keeps_the_camera_awake_unless_it_never_sleeps() {
+    let auto_power_off = ReadWriteSettingType::AUTO_POWER_OFF as u8;
+    let mut frames = handshake(CAMERA_ONLY);
+    for previous in [5, 0] {
+        frames.push(Frame::sent(Packet::with_data(SID::PARAMETER_RW, vec![auto_power_off, 0, 0, 0, 0, 0]).pack()));
+        frames.push(Frame::received(Packet::response(SID::PARAMETER_RW, vec![0, 0, auto_power_off, previous, 0, 0, 0]).pack()));
+        if previous != 0 {
+            frames.push(Frame::sent(Packet::with_data(SID::AUTO_SLEEP_SETTINGS, vec![30]).pack()));
+            frames.push(Frame::received(Packet::response(SID::AUTO_SLEEP_SETTINGS, vec![0x00]).pack()));
+            frames.push(Frame::sent(Packet::with_data(SID::AUTO_SLEEP_SETTINGS, vec![previous]).pack()));
+            frames.push(Frame::received(Packet::response(SID::AUTO_SLEEP_SETTINGS, vec![0x00]).pack()));
+        }
+    }
+
+    let mut camera = Camera::from_transport(Box::new(ReplayTransport::new(Recording { frames }))).await.unwrap();
+    for _ in 0..2 {
+        let stay_awake = camera.stay_awake().await.unwrap();
+        camera.restore_auto_sleep(stay_awake).await.unwrap();
+    }
+    assert!(matches!(
+        camera.parameter_write(ReadWriteSettingType::AUTO_POWER_OFF, 256).await,
+        Err(CameraError::InvalidParameter(SID::AUTO_SLEEP_SETTINGS))
+    ));
+}
+
+// A camera with image transfer that took two interval shots, with `queued`
+// photos waiting for auto upload
+fn interval_download(queued: u8) -> Vec<Frame> {
+    let image_support = SupportFunctionInfoType::IMAGE_SUPPORT_INFO as u8;
+    let mut frames = handshake_with([0, 0, 1, 0, 0, 0, 1, 0], vec![
+        Frame::sent(Packet::with_type(SID::SUPPORT_FUNCTION_INFO, image_support).pack()),
+        Frame::received(Packet::response(SID::SUPPORT_FUNCTION_INFO, vec![0, image_support, 0x03, 0x20, 0x02, 0x58, 0, 0, 0, 0, 0x10, 0]).pack()),
+    ]);
+    frames.push(Frame::sent(Packet::with_sid(SID::CHECK_CAMERA_STATUS).pack()));
+    frames.push(Frame::received(Packet::response(SID::CHECK_CAMERA_STATUS, vec![0, 0, 0xff, 0xff]).pack()));
+    frames.push(Frame::sent(Packet::with_sid(SID::INTERVAL_RECORD_STATUS).pack()));
+    frames.push(Frame::received(Packet::response(SID::INTERVAL_RECORD_STATUS, vec![0, 0, 0, 2, 0, 2]).pack()));
+    frames.push(Frame::sent(Packet::with_type(SID::SUPPORT_FUNCTION_INFO, SupportFunctionInfoType::CAMERA_FUNCTION_INFO as u8).pack()));
+    frames.push(Frame::received(Packet::response(SID::SUPPORT_FUNCTION_INFO, vec![0, 4, 0x07, 80, queued, 0, 0, 0]).pack()));
+    frames
+}
+
+#[tokio::test]
+async fn refuses_interval_download_with_older_photos_queued() {
+    let mut camera = Camera::from_transport(Box::new(ReplayTransport::new(Recording { frames: interval_download(3) }))).await.unwrap();
+    assert!(matches!(camera.interval_record_download().await, Err(CameraError::PhotosQueued { queued: 3, shots: 2 })));
+
+    let auto_power_off = ReadWriteSettingType::AUTO_POWER_OFF as u8;
+    let mut frames = interval_download(2);
+    frames.push(Frame::sent(Packet::with_data(SID::PARAMETER_RW, vec![auto_power_off, 0, 0, 0, 0, 0]).pack()));
+    frames.push(Frame::received(Packet::response(SID::PARAMETER_RW, vec![0, 0, auto_power_off, 0, 0, 0, 0]).pack()));
+    for shot in [0xa0, 0xa1] {
+        frames.push(Frame::sent(Packet::with_sid(SID::IMAGE_AUTO_UPLOAD_INFO).pack()));
+        frames.push(Frame::received(Packet::response(SID::IMAGE_AUTO_UPLOAD_INFO, vec![0]).pack()));
+        frames.push(Frame::sent(Packet::with_data(SID::IMAGE_AUTO_UPLOAD_START, vec![0; 4]).pack()));
+        frames.push(Frame::received(Packet::response(SID::IMAGE_AUTO_UPLOAD_START, vec![0, 0, 0, 1]).pack()));
+        frames.push(Frame::sent(Packet::with_data(SID::IMAGE_AUTO_UPLOAD_DATA, vec![0; 4]).pack()));
+        frames.push(Frame::received(Packet::response(SID::IMAGE_AUTO_UPLOAD_DATA, vec![0, 0, 0, 0, shot]).pack()));
+        frames.push(Frame::sent(Packet::with_sid(SID::IMAGE_AUTO_UPLOAD_END).pack()));
+        frames.push(Frame::received(Packet::response(SID::IMAGE_AUTO_UPLOAD_END, vec![0]).pack()));
+        frames.push(Frame::sent(Packet::with_sid(SID::IMAGE_AUTO_UPLOAD_COMPLETE).pack()));
+        frames.push(Frame::received(Packet::response(SID::IMAGE_AUTO_UPLOAD_COMPLETE, vec![0]).pack()));
+    }
+    let mut camera = Camera::from_transport(Box::new(ReplayTransport::new(Recording { frames }))).await.unwrap();
+    assert_eq!(camera.interval_record_download().await.unwrap(), vec![vec![0xa0], vec![0xa1]]);
+}
+
+// Synthetic replies following the inferred CameraLogStartResponse layout
+#[tokio::test]
+async fn reads_camera_log_in_chunks() {
+    let mut frames = handshake(CAMERA_ONLY);
+    frames.push(Frame::sent(Packet::with_sid(SID::CAMERA_LOG_DATE_START).pack()));
+    frames.push(Frame::received(Packet::response(SID::CAMERA_LOG_DATE_START, vec![0, 0, 0, 0, 0, 5]).pack()));
+    for (index, chunk) in [vec![1, 2, 3], vec![4, 5]].into_iter().enumerate() {
+        frames.push(Frame::sent(Packet::with_data(SID::CAMERA_LOG_DATE_DATA, vec![0, 0, 0, index as u8]).pack()));
+        frames.push(Frame::received(Packet::response(SID::CAMERA_LOG_DATE_DATA, [vec![0, 0, 0, index as u8], chunk].concat()).pack()));
+    }
+    frames.push(Frame::sent(Packet::with_sid(SID::CAMERA_LOG_FILTER_START).pack()));
+    frames.push(Frame::received(Packet::response(SID::CAMERA_LOG_FILTER_START, vec![0, 0, 0]).pack()));
+
+    let mut camera = Camera::from_transport(Box::new(ReplayTransport::new(Recording { frames }))).await.unwrap();
+    assert_eq!(camera.camera_log(CameraLogType::DATE).await.unwrap(), vec![1, 2, 3, 4, 5]);
+    assert!(matches!(camera.camera_log(CameraLogType::FILTER).await, Err(CameraError::Decode(_))));
+}
+
+#[test]
+fn recording_text_round_trip() {
+    let text = "# comment\n0.000000 SENT 416200070002b8\n\n0.125000 RECV 6142000800020055\n";
+    let recording = Recording::from_text(text).unwrap();
+    assert_eq!(recording.frames.len(), 2);
+    assert_eq!(recording.frames[1].data, vec![0x61, 0x42, 0x00, 0x08, 0x00, 0x02, 0x00, 0x55]);
+    assert_eq!(Recording::from_text(&recording.to_text()).unwrap(), recording);
+    assert!(Recording::from_text("0.0 SENT 4162\n0.1 XMIT 00\n").is_err());
+    for line in ["-1 SENT 00", "0 SENT 0é0", "NaN SENT 00", "1e300 RECV 00", "0.5 SENT"] {
+        assert!(Recording::from_text(line).is_err(), "{}", line);
+    }
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "serde")]
+
+use instax_pal::*;
+use instax_pal::profile::Profile;
+
+#[test]
+fn camera_function_info_round_trips() {
+    let info = CameraFunctionInfo {
+        battery_level: 3,
+        is_charging: true,
+        battery_capacity: 80,
+        auto_image_transfer_count: 2,
+        charger_state: ChargerStateType::CHARGING,
+        camera_error_type: CameraErrorType::NO_ERROR,
+        camera_status: Some(CameraStatusType::IDLE),
+    };
+    let json = serde_json::to_string(&info).unwrap();
+    assert!(json.contains("\"charger_state\":\"CHARGING\""));
+    assert_eq!(serde_json::from_str::<CameraFunctionInfo>(&json).unwrap(), info);
+}
+
+#[test]
+fn device_info_bytes_are_hex() {
+    let info = DeviceInfo {
+        model_number: "FI033".to_string(),
+        system_id: vec![0x01, 0xab],
+        ..Default::default()
+    };
+    let value: serde_json::Value = serde_json::to_value(&info).unwrap();
+    assert_eq!(value["model_number"], "FI033");
+    assert_eq!(value["system_id"], "01ab");
+    assert_eq!(value["identify_information"], "");
+    let decoded: DeviceInfo = serde_json::from_value(value).unwrap();
+    assert_eq!(decoded.system_id, vec![0x01, 0xab]);
+}
+
+#[test]
+fn profile_settings_are_keyed_by_name() {
+    let mut profile = Profile::default();
+    profile.settings.insert(ReadWriteSettingType::FLASH_SETTING, 1);
+    let json = serde_json::to_string(&profile).unwrap();
+    assert_eq!(json, "{\"settings\":{\"FLASH_SETTING\":1}}");
+    assert_eq!(serde_json::from_str::<Profile>(&json).unwrap(), profile);
+}
@@ -0,0 +1,25 @@
+use std::fs;
+use instax_pal::upload::{LocalFileBackend, UploadBackend, UploadError};
+
+#[test]
+fn requires_an_http_base_url() {
+    for url in ["file:///tmp/prints", "/tmp/prints", ""] {
+        assert!(matches!(LocalFileBackend::new("prints", url), Err(UploadError::InvalidBaseUrl(_))), "{}", url);
+    }
+    let backend = LocalFileBackend::new("prints", "http://192.168.1.2:8000/").unwrap();
+    assert_eq!(backend.base_url, "http://192.168.1.2:8000");
+}
+
+#[test]
+fn writes_picture_audio_and_page() {
+    let directory = std::env::temp_dir().join(format!("instax_pal_upload_{}", std::process::id()));
+    let backend = LocalFileBackend::new(&directory, "https://prints.example").unwrap();
+    let url = backend.upload(&[0xff, 0xd8], &[1, 2, 3]).unwrap();
+
+    let id = url.strip_prefix("https://prints.example/").and_then(|page| page.strip_suffix(".html")).unwrap();
+    assert_eq!(fs::read(directory.join(format!("{}.jpg", id))).unwrap(), vec![0xff, 0xd8]);
+    assert_eq!(fs::read(directory.join(format!("{}.audio", id))).unwrap(), vec![1, 2, 3]);
+    let page = fs::read_to_string(directory.join(format!("{}.html", id))).unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+    assert!(page.contains(&format!("src=\"{}.audio\"", id)));
+}